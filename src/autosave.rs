@@ -1,55 +1,138 @@
 use std::io::{stderr, Write};
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, Condvar, Mutex};
+use std::sync::{Arc, Mutex};
 use std::thread::{self, JoinHandle};
 use std::time::Duration;
 
+use chrono::Local;
+
 use crate::buffer::Buffer;
 use crate::config::Config;
+use crate::event::{AppEvent, EventSender, Shutdown};
+use crate::git::commit_file;
+use crate::hooks::{HookContext, HookEvent};
 
 /// Starts autosave thread.
 /// Sleeps between loops by user-configurable amount.
+/// The outcome of each save is sent to the render loop, so errors can be shown to the user
 pub fn start_autosave_thread(
     buffer: Arc<Mutex<Buffer>>,
-    running_handle: Arc<AtomicBool>,
-    condvar: Arc<Condvar>,
-    condmut: Arc<Mutex<()>>,
+    events: EventSender,
+    shutdown: Shutdown,
     config: &Config,
 ) -> JoinHandle<()> {
     let autosave_interval = config.autosave_interval.clone();
+    let snapshots = config.snapshots.clone();
+    let hooks = config.hooks.clone();
+    let git_message =
+        (config.git_commit && config.git_commit_on_autosave).then(|| config.git_message.clone());
     thread::spawn(move || {
         let autosave_interval = Duration::from_secs(autosave_interval.into());
-        while running_handle.load(Ordering::SeqCst) {
+        loop {
+            // Copy text under the lock, and write it after releasing it so input isn't blocked
+            let mut pending = None;
+            let mut commit = None;
+            let mut hook_context = None;
             {
-                // Write buffer to file
-                let mut buffer = buffer.lock().unwrap();
-                buffer.save().unwrap_or_else(|err| {
-                    // TODO: notify about save error
-                    buffer
-                        .set_message(Some(String::from("Error when saving; saving to .bak file")));
-                    // TODO: Set error marker or save message for user review after exiting program
-                    // tell them that backup file was saved
-                    stderr()
-                        .write_all(
-                            format!("Encountered error when saving file: {}", err).as_bytes(),
-                        )
-                        .unwrap_or(());
-                    buffer.save_backup().unwrap_or_else(|err| {
+                let buffer = buffer.lock().unwrap();
+                if buffer.modified() {
+                    pending = Some(buffer.pending_save());
+                    hook_context = Some(HookContext::new(&buffer));
+                    // The file of the buffer changes when switching chapters of a project
+                    commit = git_message.as_ref().map(|template| {
+                        (buffer.path().to_path_buf(), buffer.commit_message(template))
+                    });
+                }
+            }
+
+            // Write buffer to file
+            if let (Some(pending), Some(hook_context)) = (pending, hook_context) {
+                match pending.write() {
+                    Ok(()) => {
+                        // Stays modified if there was typing while writing
+                        buffer.lock().unwrap().mark_saved(&pending);
+                        _ = events.send(AppEvent::SaveResult(Ok(())));
+                        // A slow hook must not hold up the next autosave
+                        hooks.spawn(HookEvent::Save, hook_context, &events);
+                        // Only snapshot when something new was written
+                        if let Some(snapshots) = snapshots.as_ref() {
+                            let taken = snapshots.of(pending.path()).and_then(|snapshots| {
+                                snapshots.take(pending.path(), &Local::now())
+                            });
+                            if let Err(err) = taken {
+                                _ = events.send(AppEvent::SaveResult(Err(String::from(
+                                    "Error when saving snapshot",
+                                ))));
+                                stderr()
+                                    .write_all(
+                                        format!("Encountered error when saving snapshot: {}", err)
+                                            .as_bytes(),
+                                    )
+                                    .unwrap_or(());
+                            }
+                        }
+                    }
+                    Err(err) => {
+                        commit = None;
+                        // TODO: notify about save error
+                        // Shown in full, e.g. characters the encoding of the file can't hold
+                        _ = events.send(AppEvent::SaveResult(Err(format!(
+                            "Error when saving: {}; saving to {}",
+                            err,
+                            match snapshots {
+                                Some(_) => "a snapshot",
+                                None => ".bak file",
+                            }
+                        ))));
+                        // TODO: Set error marker or save message for user review after exiting program
+                        // tell them that backup file was saved
                         stderr()
                             .write_all(
-                                format!(
+                                format!("Encountered error when saving file: {}", err).as_bytes(),
+                            )
+                            .unwrap_or(());
+                        // Snapshots replace the single .bak file
+                        let backup = match snapshots.as_ref() {
+                            Some(snapshots) => snapshots.of(pending.path()).and_then(|snapshots| {
+                                snapshots
+                                    .save(&Local::now(), |snapshot| pending.write_copy(snapshot))
+                            }),
+                            None => pending
+                                .write_backup()
+                                .map(|()| pending.backup_path().to_path_buf()),
+                        };
+                        // Stays modified, so the file is saved again before exiting
+                        match backup {
+                            Ok(path) => buffer.lock().unwrap().set_backup_path(path),
+                            Err(err) => stderr()
+                                .write_all(
+                                    format!(
                                     "CRITICAL: Encountered error when attemping to save backup: {}",
                                     err
                                 )
-                                .as_bytes(),
-                            )
-                            .unwrap_or(());
-                    });
-                });
+                                    .as_bytes(),
+                                )
+                                .unwrap_or(()),
+                        }
+                        hooks.spawn(HookEvent::SaveError, hook_context.with_error(&err), &events);
+                    }
+                }
+            }
+
+            // Commit without holding the buffer lock, git may be slow
+            if let Some((path, message)) = commit {
+                if let Err(err) = commit_file(&path, &message) {
+                    _ = events.send(AppEvent::SaveResult(Err(String::from(
+                        "Error when committing to git",
+                    ))));
+                    stderr()
+                        .write_all(format!("Encountered error when committing: {}", err).as_bytes())
+                        .unwrap_or(());
+                }
             }
 
-            let guard = condmut.lock().unwrap();
-            let _ = condvar.wait_timeout(guard, autosave_interval).unwrap();
+            if shutdown.wait(autosave_interval) {
+                break;
+            }
         }
     })
 }
@@ -0,0 +1,291 @@
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+#[cfg(unix)]
+use std::os::unix::fs::FileTypeExt;
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, RecvTimeoutError, SyncSender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// How often the broadcast thread checks for new watchers
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(100);
+/// Watchers that take nothing of an update for this long are disconnected
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+/// Updates waiting for the broadcast thread before new ones are dropped
+const UPDATE_BACKLOG: usize = 16;
+
+/// Where a session is broadcast: a local TCP address like `127.0.0.1:7878`, or the path of a Unix socket
+#[derive(Debug, Clone, PartialEq)]
+pub enum BroadcastAddr {
+    Tcp(SocketAddr),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl BroadcastAddr {
+    pub fn parse(addr: &str) -> io::Result<Self> {
+        match addr.parse::<SocketAddr>() {
+            // Anyone who can connect can read the text
+            Ok(socket_addr) if !socket_addr.ip().is_loopback() => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{:?} is not a local address", addr),
+            )),
+            Ok(socket_addr) => Ok(BroadcastAddr::Tcp(socket_addr)),
+            #[cfg(unix)]
+            Err(_) => Ok(BroadcastAddr::Unix(PathBuf::from(addr))),
+            #[cfg(not(unix))]
+            Err(_) => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{:?} is not a TCP address", addr),
+            )),
+        }
+    }
+}
+
+/// State of the session sent to watchers, one JSON object per line.
+/// The whole text is sent every time, so watchers can join at any point
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BroadcastUpdate {
+    pub text: String,
+    /// Milliseconds since the session started
+    pub elapsed_ms: u64,
+}
+
+/// Remove a socket left behind at path if a previous session crashed.
+/// Anything else at path, like a file mistyped as the socket path, is left alone
+#[cfg(unix)]
+pub fn remove_stale_socket(path: &Path) -> io::Result<()> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => fs::remove_file(path),
+        Ok(_) => Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{:?} exists and is not a socket", path),
+        )),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(err),
+    }
+}
+
+enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener, PathBuf),
+}
+
+impl Listener {
+    fn bind(addr: &BroadcastAddr) -> io::Result<Self> {
+        let listener = match addr {
+            BroadcastAddr::Tcp(socket_addr) => {
+                let listener = TcpListener::bind(socket_addr)?;
+                listener.set_nonblocking(true)?;
+                Listener::Tcp(listener)
+            }
+            #[cfg(unix)]
+            BroadcastAddr::Unix(path) => {
+                remove_stale_socket(path)?;
+                let listener = UnixListener::bind(path)?;
+                listener.set_nonblocking(true)?;
+                Listener::Unix(listener, path.clone())
+            }
+        };
+        Ok(listener)
+    }
+
+    /// Accept a waiting watcher, if any
+    fn accept(&self) -> io::Result<Option<Box<dyn Write + Send>>> {
+        let result: io::Result<Box<dyn Write + Send>> = match self {
+            Listener::Tcp(listener) => listener.accept().and_then(|(stream, _)| {
+                stream.set_nonblocking(false)?;
+                stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+                Ok(Box::new(stream) as Box<dyn Write + Send>)
+            }),
+            #[cfg(unix)]
+            Listener::Unix(listener, _) => listener.accept().and_then(|(stream, _)| {
+                stream.set_nonblocking(false)?;
+                stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+                Ok(Box::new(stream) as Box<dyn Write + Send>)
+            }),
+        };
+        match result {
+            Ok(stream) => Ok(Some(stream)),
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Listener::Unix(_, path) = self {
+            _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Publishes updates of the session to watchers connected to the broadcast socket.
+/// The broadcast thread stops when this is dropped
+pub struct Broadcaster {
+    sender: SyncSender<BroadcastUpdate>,
+}
+
+impl Broadcaster {
+    /// Start listening for watchers on a thread of its own
+    pub fn start(addr: &BroadcastAddr) -> io::Result<(Self, JoinHandle<()>)> {
+        let listener = Listener::bind(addr)?;
+        let (sender, receiver) = mpsc::sync_channel::<BroadcastUpdate>(UPDATE_BACKLOG);
+        let handle = thread::spawn(move || {
+            let mut watchers: Vec<Box<dyn Write + Send>> = Vec::new();
+            let mut latest: Option<String> = None;
+            loop {
+                while let Ok(Some(mut watcher)) = listener.accept() {
+                    // New watchers start from the latest state
+                    let caught_up = latest
+                        .as_ref()
+                        .map_or(true, |line| watcher.write_all(line.as_bytes()).is_ok());
+                    if caught_up {
+                        watchers.push(watcher);
+                    }
+                }
+                let update = match receiver.recv_timeout(ACCEPT_INTERVAL) {
+                    Ok(update) => update,
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                };
+                // Only the most recent update matters if several are waiting
+                let update = receiver.try_iter().last().unwrap_or(update);
+                let Ok(line) = serde_json::to_string(&update) else {
+                    continue;
+                };
+                let line = line + "\n";
+                watchers.retain_mut(|watcher| watcher.write_all(line.as_bytes()).is_ok());
+                latest = Some(line);
+            }
+        });
+        Ok((Self { sender }, handle))
+    }
+
+    /// Never blocks the session. If the broadcast thread is behind, the update is dropped, and the next one
+    /// brings watchers up to date as it holds the whole text
+    pub fn publish(&self, update: BroadcastUpdate) {
+        _ = self.sender.try_send(update);
+    }
+}
+
+/// Connect to a broadcast, returning the stream of updates
+pub fn watch(
+    addr: &BroadcastAddr,
+) -> io::Result<impl Iterator<Item = io::Result<BroadcastUpdate>>> {
+    let reader: Box<dyn BufRead + Send> = match addr {
+        BroadcastAddr::Tcp(socket_addr) => {
+            Box::new(BufReader::new(TcpStream::connect(socket_addr)?))
+        }
+        #[cfg(unix)]
+        BroadcastAddr::Unix(path) => Box::new(BufReader::new(UnixStream::connect(path)?)),
+    };
+    Ok(reader.lines().map(|line| {
+        serde_json::from_str(&line?).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn watchers_receive_updates() {
+        let dir = tempfile::tempdir().unwrap();
+        let addr = BroadcastAddr::parse(dir.path().join("hemm.sock").to_str().unwrap()).unwrap();
+        let (broadcaster, handle) = Broadcaster::start(&addr).unwrap();
+        let first = BroadcastUpdate {
+            text: String::from("Once upon"),
+            elapsed_ms: 1000,
+        };
+        broadcaster.publish(first.clone());
+        // Give the broadcast thread time to handle the update before the watcher joins
+        thread::sleep(ACCEPT_INTERVAL * 2);
+
+        let mut updates = watch(&addr).unwrap();
+        assert_eq!(updates.next().unwrap().unwrap(), first);
+        let second = BroadcastUpdate {
+            text: String::from("Once upon a time"),
+            elapsed_ms: 2000,
+        };
+        broadcaster.publish(second.clone());
+        assert_eq!(updates.next().unwrap().unwrap(), second);
+
+        drop(broadcaster);
+        handle.join().unwrap();
+        // The stream ends when the session does
+        assert!(updates.next().is_none());
+    }
+
+    #[test]
+    fn disconnects_watchers_that_fall_behind() {
+        let dir = tempfile::tempdir().unwrap();
+        let addr = BroadcastAddr::parse(dir.path().join("hemm.sock").to_str().unwrap()).unwrap();
+        let (broadcaster, handle) = Broadcaster::start(&addr).unwrap();
+        // Never reads, so the socket fills up
+        let mut slow = watch(&addr).unwrap();
+        thread::sleep(ACCEPT_INTERVAL * 2);
+        broadcaster.publish(BroadcastUpdate {
+            text: "word ".repeat(1 << 20),
+            elapsed_ms: 1000,
+        });
+        // The first write fills the socket, the next one times out
+        thread::sleep(WRITE_TIMEOUT * 3);
+        // The cut off update is all the slow watcher gets before the stream ends
+        assert!(slow.next().unwrap().is_err());
+        assert!(slow.next().is_none());
+
+        let mut updates = watch(&addr).unwrap();
+        let last = BroadcastUpdate {
+            text: String::from("The end"),
+            elapsed_ms: 2000,
+        };
+        thread::sleep(ACCEPT_INTERVAL * 2);
+        broadcaster.publish(last.clone());
+        assert!(updates.any(|update| update.unwrap() == last));
+
+        drop(broadcaster);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn replaces_only_sockets() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hemm.sock");
+        let addr = BroadcastAddr::Unix(path.clone());
+        // Left behind by a session that crashed
+        drop(UnixListener::bind(&path).unwrap());
+        let (broadcaster, handle) = Broadcaster::start(&addr).unwrap();
+        drop(broadcaster);
+        handle.join().unwrap();
+
+        fs::write(&path, "Chapter one").unwrap();
+        let err = Broadcaster::start(&addr).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "Chapter one");
+    }
+
+    #[test]
+    fn parses_addresses() {
+        assert_eq!(
+            BroadcastAddr::parse("127.0.0.1:7878").unwrap(),
+            BroadcastAddr::Tcp(SocketAddr::from(([127, 0, 0, 1], 7878)))
+        );
+        assert_eq!(
+            BroadcastAddr::parse("/tmp/hemm.sock").unwrap(),
+            BroadcastAddr::Unix(PathBuf::from("/tmp/hemm.sock"))
+        );
+        // The text would be readable from other machines
+        assert!(BroadcastAddr::parse("0.0.0.0:7878").is_err());
+    }
+}
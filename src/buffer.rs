@@ -1,18 +1,46 @@
 use std::{
     fmt::Debug,
-    fs,
-    io::{self, Write},
-    path::PathBuf,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
     time::{Duration, Instant},
 };
 
+use chrono::Local;
+
 use tui::{
-    style::Style,
-    widgets::{Block, Borders},
+    style::{Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Paragraph, Wrap},
 };
 use tui_textarea::TextArea;
 
-use crate::config::Config;
+use crate::config::{bak_path, Config};
+use crate::crypto::{is_encrypted, Cipher};
+use crate::file_format::FileFormat;
+use crate::git::commit_message;
+use crate::stats::SessionStats;
+use crate::template::{datetime_field, render_template};
+use crate::theme::Theme;
+use crate::wrap::{unwrap_text, wrap_lines};
+use crate::writer::{FileWriter, Head, PendingSave};
+
+/// Shown in blind mode in place of text that has been hidden
+const BLIND_PLACEHOLDER: &str = "...";
+
+/// Set in append-only mode, where existing file contents are never rewritten
+struct AppendOnly {
+    /// Inserted between the existing contents and the new text
+    separator: String,
+}
+
+/// Byte index in text where its last `count` lines start
+fn tail_start(text: &str, count: usize) -> usize {
+    let body = text.strip_suffix('\n').unwrap_or(text);
+    body.rmatch_indices('\n')
+        .nth(count.saturating_sub(1))
+        .map_or(0, |(index, _)| index + 1)
+}
 
 // Code largely adapted from tui-textarea editor example
 // https://github.com/rhysd/tui-textarea/blob/d4bbccbfdbf8c8be933c30c1f7ee61be2f18b6b4/examples/editor.rs
@@ -21,20 +49,40 @@ use crate::config::Config;
 pub struct Buffer {
     // As far as I understand, this *must* be static in order to share with multiple threads
     // because the compiler cannot know when a thread will stop referencing it
-    /// TextArea tui widget
+    /// TextArea tui widget. Edit text through `Buffer::edit`, which keeps the word count up to date
     pub textarea: TextArea<'static>,
-    /// Path to file output
-    path: PathBuf,
-    /// Path to backup file
-    back_path: PathBuf,
+    /// Writes the text to disk
+    writer: Arc<FileWriter>,
     /// Modified since last save
     modified: bool,
+    /// Incremented on every modification, so a save can tell if the text changed while it was written
+    revision: u64,
     /// Whether or not file existed at beginning of program start
     file_already_existed: bool,
     /// A temporary message for the user
     message: Option<String>,
     /// Instant of last message, to check if expired
     message_instant: Option<Instant>,
+    /// Styles used for rendering
+    theme: Theme,
+    /// Number of words when the file was opened
+    initial_word_count: usize,
+    /// Words written this session in files opened before this one, e.g. other chapters
+    earlier_session_words: usize,
+    /// Statistics of the writing session
+    stats: SessionStats,
+    /// Set when appending to an existing file. The textarea then only holds the new text
+    append_only: Option<AppendOnly>,
+    /// Number of words in the part of the file not loaded into the textarea
+    head_word_count: usize,
+    /// Number of characters in the part of the file not loaded into the textarea
+    head_char_count: usize,
+    /// Number of words in the textarea, updated on each edit
+    text_word_count: usize,
+    /// Column paragraphs are hard-wrapped at when saving
+    wrap_column: Option<usize>,
+    /// Where the text was kept when saving to the file last failed, reported on exit
+    backup_path: Option<PathBuf>,
 }
 
 impl Debug for Buffer {
@@ -49,17 +97,100 @@ impl Debug for Buffer {
 
 impl Buffer {
     pub fn new(config: &Config) -> io::Result<Self> {
-        let path = config.get_output_path();
+        Self::open(config, config.get_output_path(), None)
+    }
+
+    /// Open encrypted file, or create a new one encrypted with passphrase
+    pub fn new_encrypted(config: &Config, passphrase: &str) -> io::Result<Self> {
+        Self::open(config, config.get_output_path(), Some(passphrase))
+    }
+
+    /// Open a file other than the output file with the options of config, e.g. a chapter of a
+    /// project
+    pub fn open_path(config: &Config, path: &Path) -> io::Result<Self> {
+        Self::open(config, path.to_path_buf(), None)
+    }
 
+    fn open(config: &Config, path: PathBuf, passphrase: Option<&str>) -> io::Result<Self> {
         let file_already_existed = path.exists();
-        let mut textarea = if let Ok(md) = path.metadata() {
+        let mut append_only = None;
+        let mut cipher = None;
+        let mut format = FileFormat::default();
+        let mut head = None;
+        let mut head_word_count = 0;
+        let mut head_char_count = 0;
+        let textarea = if let Ok(md) = path.metadata() {
             if md.is_file() {
-                let contents = fs::read_to_string(path.clone())?;
-                let mut textarea = TextArea::from(contents.lines());
-                // When resuming file, move cursor to end
-                textarea.move_cursor(tui_textarea::CursorMove::Bottom);
-                textarea.move_cursor(tui_textarea::CursorMove::End);
-                textarea
+                let data = fs::read(path.clone())?;
+                let data = match passphrase {
+                    // An empty file can't hold the salt, so treat it as a new file
+                    Some(passphrase) if data.is_empty() => {
+                        cipher = Some(Cipher::new(passphrase)?);
+                        data
+                    }
+                    Some(passphrase) => {
+                        let file_cipher = Cipher::for_encrypted(passphrase, &data)?;
+                        let data = file_cipher.decrypt(&data)?;
+                        cipher = Some(file_cipher);
+                        data
+                    }
+                    None if is_encrypted(&data) => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("{:?} is encrypted, open it with --encrypt true", path),
+                        ));
+                    }
+                    None => data,
+                };
+                let (mut contents, file_format) = FileFormat::decode(&data);
+                format = file_format;
+                let is_large = cipher.is_none()
+                    && config
+                        .large_file_threshold
+                        .map_or(false, |threshold| md.len() > threshold);
+                if config.append_only && !contents.is_empty() {
+                    let now = Local::now();
+                    let separator = render_template(&config.append_separator, |name| {
+                        datetime_field(name, &now)
+                    });
+                    head_word_count = contents.split_whitespace().count();
+                    head_char_count = contents.chars().count() + separator.chars().count();
+                    head = Some(Head {
+                        len: data.len() as u64,
+                        text: Some(contents),
+                    });
+                    append_only = Some(AppendOnly { separator });
+                    TextArea::default()
+                } else {
+                    if is_large {
+                        // Only the last lines are loaded, the rest of the file is left on disk
+                        let start = tail_start(&contents, config.tail_lines);
+                        if start > 0 {
+                            let head_text = &contents[..start];
+                            head_word_count = head_text.split_whitespace().count();
+                            head_char_count = head_text.chars().count();
+                            head = Some(Head {
+                                len: format.encode(head_text, true)?.len() as u64,
+                                text: None,
+                            });
+                            contents.drain(..start);
+                        }
+                    }
+                    // Paragraphs are edited as one line each, however they were wrapped on disk
+                    if let Some(width) = config.wrap_column {
+                        contents = unwrap_text(&contents, width);
+                    }
+                    // When resuming file, insert header at end
+                    if let Some(header) = config.header.as_ref().filter(|_| !contents.is_empty()) {
+                        let now = Local::now();
+                        contents += &render_template(header, |name| datetime_field(name, &now));
+                    }
+                    let mut textarea = TextArea::from(contents.lines());
+                    // When resuming file, move cursor to end
+                    textarea.move_cursor(tui_textarea::CursorMove::Bottom);
+                    textarea.move_cursor(tui_textarea::CursorMove::End);
+                    textarea
+                }
             } else {
                 // Path exists but is not a file
                 return Err(io::Error::new(
@@ -68,51 +199,275 @@ impl Buffer {
                 ));
             }
         } else {
-            TextArea::default() // File does not exist
+            // File does not exist
+            if let Some(passphrase) = passphrase {
+                cipher = Some(Cipher::new(passphrase)?);
+            }
+            TextArea::default()
         };
-        textarea.set_hard_tab_indent(config.use_hard_indent);
-        // Remove default underline style from active line
-        textarea.set_cursor_line_style(Style::default());
-        textarea.set_wrap(true);
-        let block = Block::default().borders(Borders::ALL);
-        textarea.set_block(block);
-        Ok(Self {
+        let back_path = bak_path(&path);
+        let mut buffer = Self::with_textarea(
+            config,
             textarea,
+            FileWriter {
+                path,
+                back_path,
+                format,
+                cipher,
+                head,
+                written: Mutex::default(),
+            },
+        );
+        buffer.file_already_existed = file_already_existed;
+        buffer.append_only = append_only;
+        buffer.head_word_count = head_word_count;
+        buffer.head_char_count = head_char_count;
+        buffer.initial_word_count = buffer.word_count();
+        Ok(buffer)
+    }
+
+    /// Buffer holding text that isn't read from the output file, e.g. for replaying a recording.
+    /// Saving it still writes to the output file
+    pub fn from_text(config: &Config, text: &str) -> Self {
+        let mut textarea = TextArea::from(text.lines());
+        textarea.move_cursor(tui_textarea::CursorMove::Bottom);
+        textarea.move_cursor(tui_textarea::CursorMove::End);
+        let writer = FileWriter {
             path: config.get_output_path(),
             back_path: config.get_bak_path(),
+            format: FileFormat::default(),
+            cipher: None,
+            head: None,
+            written: Mutex::default(),
+        };
+        let mut buffer = Self::with_textarea(config, textarea, writer);
+        buffer.initial_word_count = buffer.word_count();
+        buffer
+    }
+
+    fn with_textarea(config: &Config, mut textarea: TextArea<'static>, writer: FileWriter) -> Self {
+        textarea.set_hard_tab_indent(config.use_hard_indent);
+        textarea.set_style(config.theme.text);
+        // Replaces default underline style of active line
+        textarea.set_cursor_line_style(config.theme.cursor_line);
+        textarea.set_wrap(true);
+        textarea.set_block(config.theme.block());
+        let text_word_count = count_words(textarea.lines());
+        Self {
+            textarea,
+            writer: Arc::new(writer),
             modified: false,
-            file_already_existed,
+            revision: 0,
+            file_already_existed: false,
             message: None,
             message_instant: None,
-        })
+            theme: config.theme.clone(),
+            initial_word_count: 0,
+            earlier_session_words: 0,
+            stats: SessionStats::new(config.pause_threshold),
+            append_only: None,
+            head_word_count: 0,
+            head_char_count: 0,
+            text_word_count,
+            wrap_column: config.wrap_column,
+            backup_path: None,
+        }
     }
 
-    /// Save to backup filepath
+    /// Save to backup filepath. The buffer stays modified, as the file itself isn't saved
     pub fn save_backup(&mut self) -> io::Result<()> {
         // Don't bother checking modified because we want to make sure this runs
-        self.write_file(&self.back_path)?;
-        self.clear_modified();
+        self.pending_save().write_backup()?;
+        self.backup_path = Some(self.writer.back_path.clone());
         Ok(())
     }
 
+    /// Where the text was kept when saving to the file last failed, if it did
+    pub fn backup_path(&self) -> Option<&Path> {
+        self.backup_path.as_deref()
+    }
+
+    /// Record that text failed to save to the file was written to path instead
+    pub fn set_backup_path(&mut self, path: PathBuf) {
+        self.backup_path = Some(path);
+    }
+
     /// Save to final filepath
     pub fn save(&mut self) -> io::Result<()> {
         if !self.modified {
             return Ok(());
         }
-        self.write_file(&self.path)?;
+        let pending = self.pending_save();
+        pending.write()?;
         // self.set_message(Some(String::from("Backup saved")));
-        self.clear_modified();
+        self.mark_saved(&pending);
         Ok(())
     }
 
-    fn write_file(&self, path: &PathBuf) -> io::Result<()> {
-        let mut f = fs::File::create(path)?;
-        for line in self.textarea.lines() {
-            f.write_all(line.as_bytes())?;
-            f.write_all(b"\n")?;
+    /// Copy the text to be saved, so it can be written after releasing the buffer lock
+    pub fn pending_save(&self) -> PendingSave {
+        PendingSave::new(self.writer.clone(), self.new_text(), self.revision)
+    }
+
+    /// Text written after the head of the file, or the whole text if there is none.
+    /// In append-only mode, this is the separator and the new text
+    fn new_text(&self) -> String {
+        let format = &self.writer.format;
+        let wrapped;
+        let lines = match self.wrap_column {
+            Some(width) => {
+                wrapped = wrap_lines(self.textarea.lines(), width);
+                &wrapped
+            }
+            None => self.textarea.lines(),
+        };
+        match &self.append_only {
+            // Nothing new to append
+            Some(_) if lines.iter().all(|line| line.is_empty()) => String::new(),
+            Some(append_only) => {
+                format.convert_line_endings(&append_only.separator) + &format.join_lines(lines)
+            }
+            None => format.join_lines(lines),
+        }
+    }
+
+    /// Fill in git commit message template for the output file
+    pub fn commit_message(&self, template: &str) -> String {
+        commit_message(
+            template,
+            &self.writer.path,
+            self.word_count(),
+            self.session_word_count(),
+            &Local::now(),
+        )
+    }
+
+    /// Last lines of the existing file in append-only mode, shown read-only above the new text
+    pub fn existing_tail(&self, count: usize) -> Vec<&str> {
+        match self
+            .writer
+            .head
+            .as_ref()
+            .and_then(|head| head.text.as_ref())
+        {
+            Some(existing) => {
+                let lines: Vec<&str> = existing.lines().collect();
+                lines[lines.len().saturating_sub(count)..].to_vec()
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /// Insert a header at the cursor, on lines of its own followed by a blank line.
+    /// Words of the header don't count as written in this session
+    pub fn insert_header(&mut self, header: &str) {
+        let (row, _) = self.textarea.cursor();
+        // Start on a line of its own
+        if !self.textarea.lines()[row].is_empty() {
+            self.textarea.insert_newline();
+        }
+        for line in header.lines() {
+            self.textarea.insert_str(line);
+            self.textarea.insert_newline();
+        }
+        self.textarea.insert_newline();
+        self.text_word_count = count_words(self.textarea.lines());
+        self.initial_word_count += header.split_whitespace().count();
+        self.mark_modified();
+    }
+
+    /// Number of words in the file, including the parts not loaded into the textarea
+    pub fn word_count(&self) -> usize {
+        self.head_word_count + self.text_word_count
+    }
+
+    /// Number of words written since the file was opened, plus those written in the files
+    /// opened before it. Zero if more words were deleted than written
+    pub fn session_word_count(&self) -> usize {
+        self.earlier_session_words + self.word_count().saturating_sub(self.initial_word_count)
+    }
+
+    /// Continue the session of previous, which this buffer replaces
+    pub fn continue_session(&mut self, previous: Buffer) {
+        self.earlier_session_words = previous.session_word_count();
+        self.stats = previous.stats;
+        self.backup_path = previous.backup_path;
+    }
+
+    /// Number of characters in the file, counting line breaks
+    pub fn char_count(&self) -> usize {
+        let lines = self.textarea.lines();
+        let chars: usize = lines.iter().map(|line| line.chars().count()).sum();
+        self.head_char_count + chars + lines.len().saturating_sub(1)
+    }
+
+    /// Apply a keystroke that edits the text, and record it in session statistics.
+    /// `edit` may only change the line under the cursor and the lines next to it, so only those
+    /// are counted again instead of the whole file
+    pub fn edit(&mut self, edit: impl FnOnce(&mut TextArea<'static>)) {
+        let (row, _) = self.textarea.cursor();
+        let start = row.saturating_sub(1);
+        let lines_before = self.textarea.lines().len();
+        let end_before = (row + 2).min(lines_before);
+        let words_before = count_words(&self.textarea.lines()[start..end_before]);
+
+        edit(&mut self.textarea);
+
+        let lines_after = self.textarea.lines().len();
+        let end_after = (end_before + lines_after)
+            .saturating_sub(lines_before)
+            .clamp(start, lines_after);
+        let words_after = count_words(&self.textarea.lines()[start..end_after]);
+        self.text_word_count = (self.text_word_count + words_after).saturating_sub(words_before);
+        self.stats.record_keystroke(words_before, words_after);
+        self.mark_modified();
+    }
+
+    /// Get session statistics
+    pub fn stats(&self) -> &SessionStats {
+        &self.stats
+    }
+
+    /// Text visible in blind mode: the last `visible_words` words if given,
+    /// otherwise the line under the cursor.
+    /// Returns the visible text, and whether any text before it is hidden
+    pub fn blind_text(&self, visible_words: Option<usize>) -> (String, bool) {
+        let lines = self.textarea.lines();
+        match visible_words {
+            Some(count) => {
+                let text = lines.join("\n");
+                let words: Vec<&str> = text.split_whitespace().collect();
+                let hidden = words.len() > count;
+                let mut visible = words[words.len().saturating_sub(count)..].join(" ");
+                // Keep trailing whitespace so the cursor sits where the next word will start
+                if count > 0 && text.ends_with(char::is_whitespace) {
+                    visible.push(' ');
+                }
+                (visible, hidden)
+            }
+            None => {
+                let (row, _) = self.textarea.cursor();
+                (lines[row].clone(), row > 0)
+            }
         }
-        Ok(())
+    }
+
+    /// Widget rendered in place of `textarea.widget()` in blind mode
+    pub fn blind_widget(&self, visible_words: Option<usize>) -> Paragraph<'static> {
+        let (visible, hidden) = self.blind_text(visible_words);
+        let mut text = Vec::new();
+        if hidden {
+            text.push(Spans::from(Span::styled(BLIND_PLACEHOLDER, self.theme.dim)));
+        }
+        text.push(Spans::from(vec![
+            Span::raw(visible),
+            // Paragraph has no cursor, so draw one after the text
+            Span::styled(" ", Style::default().add_modifier(Modifier::REVERSED)),
+        ]));
+        Paragraph::new(text)
+            .style(self.theme.text)
+            .block(self.theme.block())
+            .wrap(Wrap { trim: false })
     }
 
     /// Get message if valid
@@ -155,6 +510,11 @@ impl Buffer {
         self.message_instant = None;
     }
 
+    /// File the buffer is saved to
+    pub fn path(&self) -> &Path {
+        &self.writer.path
+    }
+
     /// Get modified value
     pub fn modified(&self) -> bool {
         self.modified
@@ -162,7 +522,16 @@ impl Buffer {
 
     /// Set modified to true
     pub fn mark_modified(&mut self) {
-        self.modified = true
+        self.modified = true;
+        self.revision += 1;
+    }
+
+    /// Reset modified to false once pending was written, unless the text changed since it was
+    /// copied, or another file was opened in this buffer's place
+    pub fn mark_saved(&mut self, pending: &PendingSave) {
+        if pending.is_from(&self.writer) && pending.revision() == self.revision {
+            self.modified = false;
+        }
     }
 
     /// Reset modified to false
@@ -170,3 +539,276 @@ impl Buffer {
         self.modified = false
     }
 }
+
+fn count_words(lines: &[String]) -> usize {
+    lines
+        .iter()
+        .map(|line| line.split_whitespace().count())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::cli::Cli;
+
+    fn open(path: &Path, args: &[&str]) -> Buffer {
+        let args = [PathBuf::from("hemm"), path.to_path_buf()]
+            .into_iter()
+            .chain(args.iter().map(PathBuf::from));
+        Buffer::new(&Config::new(&Cli::parse_from(args)).unwrap()).unwrap()
+    }
+
+    /// Open file, then save it without typing anything
+    fn save_untouched(data: &[u8]) -> Vec<u8> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file.txt");
+        fs::write(&path, data).unwrap();
+        let mut buffer = open(&path, &[]);
+        buffer.mark_modified();
+        buffer.save().unwrap();
+        fs::read(&path).unwrap()
+    }
+
+    #[test]
+    fn saves_untouched_files_unchanged() {
+        let files: &[&[u8]] = &[
+            b"one\ntwo\n",
+            b"one\ntwo",
+            b"one\r\ntwo\r\n",
+            b"one\r\ntwo",
+            b"\xEF\xBB\xBFone\ntwo",
+            b"\xFF\xFEo\0n\0e\0\r\0\n\0",
+            b"caf\xE9\n",
+        ];
+        for &data in files {
+            assert_eq!(
+                save_untouched(data),
+                data,
+                "{:?}",
+                String::from_utf8_lossy(data)
+            );
+        }
+    }
+
+    #[test]
+    fn wraps_paragraphs_on_save_and_joins_them_on_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file.txt");
+        fs::write(
+            &path,
+            "The rain fell on the\r\nroofs all night.\r\n\r\nIt stopped.\r\n",
+        )
+        .unwrap();
+        let mut buffer = open(&path, &["--wrap", "20"]);
+        assert_eq!(
+            buffer.textarea.lines(),
+            ["The rain fell on the roofs all night.", "", "It stopped."]
+        );
+        buffer.textarea.insert_str(" Then the sun came out.");
+        buffer.mark_modified();
+        buffer.save().unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "The rain fell on the\r\nroofs all night.\r\n\r\nIt stopped. Then the\r\nsun came out.\r\n"
+        );
+    }
+
+    #[test]
+    fn large_file_loads_only_tail() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("book.txt");
+        let data: String = (1..=2000).map(|n| format!("line {}\r\n", n)).collect();
+        fs::write(&path, &data).unwrap();
+
+        let mut buffer = open(&path, &["--large-file", "1", "--tail-lines", "3"]);
+        assert_eq!(
+            buffer.textarea.lines(),
+            ["line 1998", "line 1999", "line 2000"]
+        );
+        assert_eq!(buffer.word_count(), 4000);
+
+        buffer.textarea.insert_str(" end");
+        buffer.mark_modified();
+        buffer.save().unwrap();
+        let expected = data.trim_end().to_string() + " end\r\n";
+        assert_eq!(fs::read_to_string(&path).unwrap(), expected);
+
+        // Backups hold the whole file
+        buffer.save_backup().unwrap();
+        let back_path = dir.path().join("book.txt.bak");
+        assert_eq!(fs::read_to_string(back_path).unwrap(), expected);
+    }
+
+    #[test]
+    fn encrypted_files_and_backups_hold_no_plaintext() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("diary.txt");
+        let config = Config::new(&Cli::parse_from([
+            PathBuf::from("hemm"),
+            path.clone(),
+            PathBuf::from("--encrypt"),
+            PathBuf::from("true"),
+        ]))
+        .unwrap();
+        let mut buffer = Buffer::new_encrypted(&config, "correct horse").unwrap();
+        buffer.textarea.insert_str("Dear diary");
+        buffer.mark_modified();
+        buffer.save().unwrap();
+        buffer.save_backup().unwrap();
+
+        for file in [path.clone(), dir.path().join("diary.txt.bak")] {
+            let data = fs::read(&file).unwrap();
+            assert!(is_encrypted(&data), "{:?}", file);
+            assert!(!data.windows(5).any(|window| window == b"diary"));
+        }
+        let reopened = Buffer::new_encrypted(&config, "correct horse").unwrap();
+        assert_eq!(reopened.textarea.lines(), ["Dear diary"]);
+        assert!(Buffer::new(&config).is_err());
+    }
+
+    #[test]
+    fn latin1_files_keep_text_they_cannot_hold() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        let mut data = Vec::new();
+        for n in 1..=200 {
+            data.extend_from_slice(b"caf\xE9 ");
+            data.extend_from_slice(format!("{}\n", n).as_bytes());
+        }
+        fs::write(&path, &data).unwrap();
+
+        let mut buffer = open(&path, &["--large-file", "1", "--tail-lines", "1"]);
+        buffer.textarea.insert_str(" — done");
+        buffer.mark_modified();
+        let err = buffer.save().unwrap_err();
+        assert!(err.to_string().contains("'—' can't be saved"), "{}", err);
+        assert_eq!(fs::read(&path).unwrap(), data);
+
+        // The backup holds the whole text in UTF-8
+        buffer.save_backup().unwrap();
+        let backup = fs::read_to_string(dir.path().join("notes.txt.bak")).unwrap();
+        assert!(backup.starts_with("café 1\n"));
+        assert!(backup.ends_with("café 200 — done\n"));
+        // The file itself still lacks the text, so saving is tried again on exit
+        assert!(buffer.modified());
+        assert_eq!(
+            buffer.backup_path(),
+            Some(dir.path().join("notes.txt.bak").as_path())
+        );
+    }
+
+    #[test]
+    fn small_files_are_loaded_whole() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        fs::write(&path, "one\ntwo\nthree\n").unwrap();
+        let buffer = open(&path, &["--large-file", "1", "--tail-lines", "1"]);
+        assert_eq!(buffer.textarea.lines(), ["one", "two", "three"]);
+    }
+
+    #[test]
+    fn rejects_large_file_threshold_too_large() {
+        use crate::config::ConfigErrorType;
+
+        let kb = (u64::MAX / 1024 + 1).to_string();
+        let err =
+            Config::new(&Cli::parse_from(["hemm", "notes.txt", "--large-file", &kb])).unwrap_err();
+        assert!(matches!(err.error_type, ConfigErrorType::LargeFileTooLarge));
+        let kb = (u64::MAX / 1024).to_string();
+        let config =
+            Config::new(&Cli::parse_from(["hemm", "notes.txt", "--large-file", &kb])).unwrap();
+        assert_eq!(config.large_file_threshold, Some(u64::MAX / 1024 * 1024));
+    }
+
+    #[test]
+    fn edits_keep_word_count_up_to_date() {
+        use crate::input::handle_key;
+        use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file.txt");
+        fs::write(&path, "one two\nthree\n\nfour").unwrap();
+        let mut buffer = open(&path, &[]);
+        let press = |buffer: &mut Buffer, code| {
+            handle_key(buffer, KeyEvent::new(code, KeyModifiers::NONE), false);
+            assert_eq!(buffer.word_count(), count_words(buffer.textarea.lines()));
+        };
+
+        // Joining and splitting lines and words
+        press(&mut buffer, KeyCode::Up);
+        press(&mut buffer, KeyCode::Up);
+        press(&mut buffer, KeyCode::Home);
+        press(&mut buffer, KeyCode::Backspace);
+        assert_eq!(buffer.textarea.lines()[0], "one twothree");
+        press(&mut buffer, KeyCode::Char(' '));
+        press(&mut buffer, KeyCode::Enter);
+        press(&mut buffer, KeyCode::End);
+        press(&mut buffer, KeyCode::Delete);
+        press(&mut buffer, KeyCode::Delete);
+        assert_eq!(buffer.textarea.lines(), ["one two ", "threefour"]);
+        assert_eq!(buffer.word_count(), 3);
+
+        // Only keys that edit the text are counted
+        assert_eq!(buffer.stats().keystrokes, 5);
+    }
+
+    #[test]
+    fn saves_never_overwrite_newer_text() {
+        use crate::input::type_text;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file.txt");
+        let mut buffer = open(&path, &[]);
+        let mut pending = Vec::new();
+        for word in [
+            "one", "two", "three", "four", "five", "six", "seven", "eight",
+        ] {
+            type_text(&mut buffer, word, false);
+            pending.push(buffer.pending_save());
+        }
+        // Copied in order, but written by threads racing each other, like autosave and control
+        std::thread::scope(|scope| {
+            for pending in pending.iter().rev() {
+                scope.spawn(|| pending.write().unwrap());
+            }
+        });
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "onetwothreefourfivesixseveneight\n"
+        );
+
+        // An older copy written last leaves the file as it is
+        pending[0].write().unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "onetwothreefourfivesixseveneight\n"
+        );
+    }
+
+    #[test]
+    fn typing_during_save_keeps_modified() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file.txt");
+        let mut buffer = open(&path, &[]);
+        buffer.textarea.insert_str("one");
+        buffer.mark_modified();
+
+        // Autosave copies the text, then writes it after releasing the lock
+        let pending = buffer.pending_save();
+        buffer.textarea.insert_str(" two");
+        buffer.mark_modified();
+        pending.write().unwrap();
+        buffer.mark_saved(&pending);
+        assert!(buffer.modified());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "one\n");
+
+        let pending = buffer.pending_save();
+        pending.write().unwrap();
+        buffer.mark_saved(&pending);
+        assert!(!buffer.modified());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "one two\n");
+    }
+}
@@ -1,13 +1,25 @@
+use std::collections::HashMap;
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
 use serde::{Deserialize, Serialize};
 
+use crate::export::ExportFormat;
+use crate::hooks::Hooks;
+use crate::prompt::PromptPlacement;
+use crate::snapshot::SnapshotInterval;
+use crate::theme::ThemeSpec;
+
 /// Struct representing options and arguments that user inputs to the program.
 ///
 /// Powered by clap crate annotations
 #[derive(Parser, Debug, Serialize, Deserialize)]
+#[command(subcommand_negates_reqs = true)]
 pub struct Cli {
+    #[serde(skip)]
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     #[serde(skip_serializing)]
     #[arg(
         required = true,
@@ -24,6 +36,15 @@ pub struct Cli {
     #[arg(long)]
     pub hemingway: Option<bool>,
 
+    /// Use blind mode (hemingway mode that hides previously written text, default: false)
+    #[arg(long)]
+    pub blind: Option<bool>,
+
+    /// Number of trailing words left visible in blind mode
+    /// default: only the current line is visible
+    #[arg(long, value_name = "WORDS")]
+    pub blind_words: Option<usize>,
+
     /// Output directory for file if full output path not given
     /// default: ./
     #[arg(short, long)]
@@ -43,9 +64,299 @@ pub struct Cli {
     #[arg(short, long)]
     pub timer: Option<bool>,
 
+    /// Length of the writing session, in minutes
+    #[arg(long, value_name = "MINUTES")]
+    pub duration: Option<u32>,
+
+    /// Number of words to write this session
+    #[arg(short, long, value_name = "WORDS")]
+    pub goal: Option<usize>,
+
+    /// Show the status line (default: true)
+    #[arg(long)]
+    pub status_line: Option<bool>,
+
+    /// Status line template, e.g. "{words} words | {elapsed}"
+    /// Fields: words, chars, elapsed, remaining, saved, mode, wpm, goal%,
+    /// rwpm, keystrokes, added, deleted, pauses, streak
+    /// default: "{words} words", or "{elapsed}" with --timer
+    #[arg(long, value_name = "TEMPLATE")]
+    pub status: Option<String>,
+
+    /// Gaps between keystrokes longer than this are counted as pauses, in seconds
+    /// default: 5
+    #[arg(long = "pause", value_name = "SECONDS")]
+    pub pause_threshold: Option<u32>,
+
+    /// Keep existing file contents read-only, and append new text after a separator
+    /// default: false
+    #[arg(long)]
+    pub append_only: Option<bool>,
+
+    /// Separator inserted before new text in append-only mode, e.g. "\n## {datetime}\n\n"
+    /// Fields: date, time, datetime
+    /// default: "\n"
+    #[arg(long, value_name = "TEMPLATE")]
+    pub separator: Option<String>,
+
+    /// Header inserted at the end of the file when resuming it, e.g. "\n## {datetime}\n\n"
+    /// Fields: date, time, datetime
+    #[arg(long, value_name = "TEMPLATE")]
+    pub header: Option<String>,
+
+    /// Start the session with a random writing prompt from the prompt file
+    /// default: false
+    #[arg(long)]
+    pub prompt: Option<bool>,
+
+    /// Only pick prompts with this tag, from a YAML prompt file. Implies --prompt true
+    #[arg(long, value_name = "TAG")]
+    pub prompt_tag: Option<String>,
+
+    /// File of writing prompts, one per line, or a YAML list if the name ends in .yaml
+    /// default: <config_dir>/hemm/prompts.txt
+    #[arg(long, value_name = "FILE")]
+    pub prompt_file: Option<PathBuf>,
+
+    /// Show the prompt above the text, or insert it into the text as a header
+    /// default: above
+    #[arg(long, value_name = "PLACEMENT")]
+    pub prompt_placement: Option<PromptPlacement>,
+
+    /// Files larger than this are opened in large-file mode: only their last lines are loaded for
+    /// editing, and saving rewrites only those lines. Not used for encrypted files
+    #[arg(long, value_name = "KB")]
+    pub large_file: Option<u64>,
+
+    /// Number of lines loaded for editing in large-file mode
+    /// default: 500
+    #[arg(long, value_name = "LINES")]
+    pub tail_lines: Option<usize>,
+
+    /// Hard-wrap paragraphs at this many columns when saving, and join them again when opening,
+    /// so files diff nicely. Paragraphs are separated by blank lines
+    #[arg(long, value_name = "COLUMNS")]
+    pub wrap: Option<usize>,
+
+    /// Encrypt file with a passphrase, prompted for on start. Backups and snapshots are encrypted too
+    /// default: false
+    #[arg(long)]
+    pub encrypt: Option<bool>,
+
     /// Use '\t' for tab keypress
     #[arg(short, long)]
     pub use_hard_indent: Option<bool>,
+
+    /// Use a preset of options defined in the config file
+    #[arg(short, long, value_name = "NAME")]
+    pub preset: Option<String>,
+
+    /// Named groups of options, only settable from the config file
+    #[arg(skip)]
+    pub presets: Option<HashMap<String, Cli>>,
+
+    /// Color theme: default, dark, light, solarized, or a theme from the config file
+    #[arg(long, value_name = "NAME")]
+    pub theme: Option<String>,
+
+    /// User-defined themes, only settable from the config file
+    #[arg(skip)]
+    pub themes: Option<HashMap<String, ThemeSpec>>,
+
+    /// Record sessions in the history file
+    /// default: true
+    #[arg(long)]
+    pub history: Option<bool>,
+
+    /// Custom history filepath
+    /// default: <data_dir>/hemm/history.jsonl
+    #[arg(long, value_name = "FILE")]
+    pub history_file: Option<PathBuf>,
+
+    /// Keep rotating snapshots of the file, written on autosave
+    /// default: false
+    #[arg(long)]
+    pub snapshots: Option<bool>,
+
+    /// Directory to store snapshots in
+    /// default: <data_dir>/hemm/snapshots
+    #[arg(long, value_name = "DIR")]
+    pub snapshot_dir: Option<PathBuf>,
+
+    /// How often a new snapshot is started
+    /// default: hour
+    #[arg(long, value_name = "INTERVAL")]
+    pub snapshot_interval: Option<SnapshotInterval>,
+
+    /// Number of snapshots to keep per file
+    /// default: 24
+    #[arg(long, value_name = "COUNT")]
+    pub snapshot_keep: Option<usize>,
+
+    /// Record key presses with timestamps to a file, for `hemm replay`. Not allowed with --encrypt
+    #[arg(long, value_name = "FILE")]
+    pub record: Option<PathBuf>,
+
+    /// Publish the session live for `hemm watch`, on a Unix socket path or a local TCP address
+    /// like 127.0.0.1:7878. Not allowed with --encrypt
+    #[arg(long, value_name = "ADDR")]
+    pub broadcast: Option<String>,
+
+    /// Accept JSON commands from scripts on a Unix socket at this path
+    #[arg(long, value_name = "SOCKET")]
+    pub control: Option<PathBuf>,
+
+    /// Commit file to the git repository containing it on exit
+    /// default: false
+    #[arg(long)]
+    pub git_commit: Option<bool>,
+
+    /// Also commit file on each autosave, if --git-commit is enabled
+    /// default: false
+    #[arg(long)]
+    pub git_autosave: Option<bool>,
+
+    /// Commit message template
+    /// Fields: file, words, session_words, date, time, datetime
+    /// default: "Update {file} ({words} words)"
+    #[arg(long, value_name = "TEMPLATE")]
+    pub git_message: Option<String>,
+
+    /// Title added to files made with `hemm export`
+    #[arg(long, value_name = "TITLE")]
+    pub title: Option<String>,
+
+    /// Author added to files made with `hemm export`
+    #[arg(long, value_name = "NAME")]
+    pub author: Option<String>,
+
+    /// Shell commands run on start, save, goal, exit and save errors, only settable from the
+    /// config file
+    #[arg(skip)]
+    pub hooks: Option<Hooks>,
+}
+
+/// Commands other than writing
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Print totals, averages and streaks from the session history
+    Stats(StatsArgs),
+
+    /// List or restore snapshots of a file
+    #[command(subcommand)]
+    Snapshots(SnapshotsCommand),
+
+    /// Play back a session recorded with --record, read-only
+    Replay(ReplayArgs),
+
+    /// Follow a session published with --broadcast, read-only
+    Watch(WatchArgs),
+
+    /// Write a project of several files, e.g. the chapters of a book
+    #[command(subcommand)]
+    Project(ProjectCommand),
+
+    /// Convert a file to another format, with paragraphs separated by blank lines
+    Export(ExportArgs),
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum ProjectCommand {
+    /// Open the current chapter of a project. Ctrl+PageDown and Ctrl+PageUp switch chapters
+    Open {
+        /// YAML manifest listing the chapter files
+        manifest: PathBuf,
+    },
+
+    /// Print words in each chapter and in the whole project
+    Stats {
+        /// YAML manifest listing the chapter files
+        manifest: PathBuf,
+
+        /// Print as JSON, for use in scripts
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Concatenate the chapters into one file
+    Build {
+        /// YAML manifest listing the chapter files
+        manifest: PathBuf,
+
+        /// File to write
+        /// default: output in the manifest, or the manifest path with a .txt extension
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum SnapshotsCommand {
+    /// List snapshots of file, oldest first
+    List {
+        /// File the snapshots were taken of
+        file: PathBuf,
+    },
+
+    /// Replace contents of file with a snapshot
+    Restore {
+        /// File the snapshots were taken of
+        file: PathBuf,
+
+        /// Name of snapshot, as shown by list
+        /// default: the most recent snapshot
+        snapshot: Option<String>,
+    },
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct StatsArgs {
+    /// Number of recent days to show totals for
+    #[arg(long, default_value_t = 7)]
+    pub days: usize,
+
+    /// Number of recent weeks to show totals for
+    #[arg(long, default_value_t = 4)]
+    pub weeks: usize,
+
+    /// Only show streak, counting days that met --goal words
+    #[arg(long)]
+    pub streak: bool,
+
+    /// Print as JSON, for use in scripts
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ReplayArgs {
+    /// Recording to play back
+    pub file: PathBuf,
+
+    /// Playback speed, e.g. 10 to play ten times faster than it was typed
+    #[arg(long, default_value_t = 1.0)]
+    pub speed: f64,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ExportArgs {
+    /// File to export
+    pub file: PathBuf,
+
+    /// Format to export to
+    #[arg(short, long, value_enum)]
+    pub format: ExportFormat,
+
+    /// File to write
+    /// default: file with the extension of the format
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct WatchArgs {
+    /// Unix socket path or TCP address the session is broadcast on
+    pub addr: String,
 }
 
 macro_rules! merge_fields {
@@ -65,11 +376,48 @@ impl Cli {
             path,
             config,
             hemingway,
+            blind,
+            blind_words,
             directory,
             autosave,
             autosave_interval,
             timer,
+            duration,
+            goal,
+            status_line,
+            status,
+            pause_threshold,
+            append_only,
+            separator,
+            header,
+            prompt,
+            prompt_tag,
+            prompt_file,
+            prompt_placement,
+            large_file,
+            tail_lines,
+            wrap,
+            encrypt,
             use_hard_indent,
+            theme,
+            themes,
+            history,
+            history_file,
+            snapshots,
+            snapshot_dir,
+            snapshot_interval,
+            snapshot_keep,
+            record,
+            broadcast,
+            control,
+            git_commit,
+            git_autosave,
+            git_message,
+            title,
+            author,
+            hooks,
+            preset,
+            presets,
         );
     }
 }
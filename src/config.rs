@@ -1,14 +1,31 @@
-use dirs::config_dir;
-use std::{fs::File, io::Read, path::PathBuf};
+use dirs::{config_dir, data_dir};
+use std::{
+    fmt,
+    fs::File,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    time::Duration,
+};
 use thiserror::Error;
 
+use crate::broadcast::BroadcastAddr;
 use crate::cli::Cli;
+use crate::git::DEFAULT_COMMIT_MESSAGE;
+use crate::hooks::{Hooks, MAX_TIMEOUT};
+use crate::prompt::PromptPlacement;
+use crate::snapshot::{SnapshotInterval, Snapshots};
+use crate::status::{DEFAULT_STATUS_TEMPLATE, TIMER_STATUS_TEMPLATE};
+use crate::theme::{ColorSupport, Theme, ThemeError, DEFAULT_THEME};
 
 #[derive(Debug)]
 pub struct Config {
     /// Using hemingway mode disables backspace and nav
     pub writing_mode: WritingMode,
 
+    /// Number of trailing words left visible in blind mode
+    /// If None, only the current line is visible
+    pub blind_visible_words: Option<usize>,
+
     /// Output name for file
     /// If None, output path will be generated from pattern
     output_name: PathBuf,
@@ -17,36 +34,169 @@ pub struct Config {
     pub output_dir: PathBuf,
 
     /// Whether or not to autosave in background
-    /// If error occurs during autosave, an attempt will be made to save to `<original_output_path>.bak`
+    /// If error occurs during autosave, an attempt will be made to save to a snapshot if snapshots
+    /// are enabled, or to `<original_output_path>.bak` otherwise
     pub use_autosave: bool,
 
     /// Number of seconds between autosave backups
     pub autosave_interval: u32,
 
     /// Whether or not to show timer in editor
+    /// Only used to pick the default status line template
     pub show_timer: bool,
 
+    /// Length of the writing session, shown as remaining time in the status line
+    pub timer_duration: Option<Duration>,
+
+    /// Number of words to write this session
+    pub word_goal: Option<usize>,
+
+    /// Whether or not to show the status line
+    /// Hiding it also hides messages, for a UI without distractions
+    pub show_status_line: bool,
+
+    /// Template of the right side of the status line, see [`crate::status::StatusFields`]
+    pub status_template: String,
+
+    /// Gaps between keystrokes longer than this are counted as pauses in session statistics
+    pub pause_threshold: Duration,
+
+    /// Whether or not existing file contents are kept read-only, with new text appended after them
+    pub append_only: bool,
+
+    /// Template of separator between existing contents and new text in append-only mode
+    pub append_separator: String,
+
+    /// Template of header inserted at the end of the file when resuming it
+    /// If None, no header is inserted
+    pub header: Option<String>,
+
+    /// File the session's prompt is picked from
+    /// If None, the session starts without a prompt
+    pub prompt_file: Option<PathBuf>,
+
+    /// Only pick prompts with this tag
+    pub prompt_tag: Option<String>,
+
+    /// Where the prompt is shown
+    pub prompt_placement: PromptPlacement,
+
+    /// Files larger than this many bytes only have their last lines loaded for editing
+    /// If None, files are always loaded whole
+    pub large_file_threshold: Option<u64>,
+
+    /// Number of lines loaded for editing in large-file mode
+    pub tail_lines: usize,
+
+    /// Column paragraphs are hard-wrapped at when saving
+    /// If None, each paragraph is saved on one line
+    pub wrap_column: Option<usize>,
+
+    /// Whether or not the file is encrypted with a passphrase
+    pub encrypt: bool,
+
     /// Whether or not <TAB> keypress should enter spaces or '\t' character
     /// default: true
     pub use_hard_indent: bool,
+
+    /// Styles used to render the editor
+    pub theme: Theme,
+
+    /// Snapshots of the output file taken on autosave. Other files opened in the session,
+    /// like chapters of a project, get their own with `Snapshots::of`
+    /// If None, no snapshots are taken
+    pub snapshots: Option<Snapshots>,
+
+    /// File that key presses are recorded to
+    /// If None, key presses are not recorded
+    pub record_path: Option<PathBuf>,
+
+    /// Address the session is published on for watchers
+    /// If None, the session is not broadcast
+    pub broadcast: Option<BroadcastAddr>,
+
+    /// Unix socket that scripts send commands to
+    /// If None, the session can't be controlled by scripts
+    pub control_socket: Option<PathBuf>,
+
+    /// Whether or not to commit the output file to its git repository on exit
+    pub git_commit: bool,
+
+    /// Whether or not to also commit on each autosave
+    pub git_commit_on_autosave: bool,
+
+    /// Template of git commit message
+    pub git_message: String,
+
+    /// Shell commands run at points of the session
+    pub hooks: Hooks,
+
+    /// File that finished sessions are recorded to
+    /// If None, sessions are not recorded
+    pub history_path: Option<PathBuf>,
 }
 
 #[derive(PartialEq, Debug)]
 pub enum WritingMode {
     Regular,
     Hemingway, // Disable backspace and navigation
+    Blind,     // Hemingway mode, and previously written text is hidden
+}
+
+impl fmt::Display for WritingMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            WritingMode::Regular => "regular",
+            WritingMode::Hemingway => "hemingway",
+            WritingMode::Blind => "blind",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl WritingMode {
+    /// Whether deletion and navigation are disabled
+    pub fn is_restricted(&self) -> bool {
+        matches!(self, WritingMode::Hemingway | WritingMode::Blind)
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             writing_mode: WritingMode::Regular,
+            blind_visible_words: None,
             output_name: "output.txt".into(),
             output_dir: "./".into(),
             use_autosave: true,
             autosave_interval: 15,
             show_timer: false,
+            timer_duration: None,
+            word_goal: None,
+            show_status_line: true,
+            status_template: String::from(DEFAULT_STATUS_TEMPLATE),
+            pause_threshold: Duration::from_secs(5),
+            append_only: false,
+            append_separator: String::from("\n"),
+            header: None,
+            prompt_file: None,
+            prompt_tag: None,
+            prompt_placement: PromptPlacement::Above,
+            large_file_threshold: None,
+            tail_lines: 500,
+            wrap_column: None,
+            encrypt: false,
             use_hard_indent: true,
+            theme: Theme::default(),
+            history_path: None,
+            snapshots: None,
+            record_path: None,
+            broadcast: None,
+            control_socket: None,
+            git_commit: false,
+            git_commit_on_autosave: false,
+            git_message: String::from(DEFAULT_COMMIT_MESSAGE),
+            hooks: Hooks::default(),
         }
     }
 }
@@ -55,25 +205,115 @@ impl Config {
     /// Create Config from arguments and user config file
     pub fn new(cli: &Cli) -> Result<Config, ConfigError> {
         let default = Self::default();
+        let show_timer = cli.timer.unwrap_or(default.show_timer);
 
-        let config = Config {
-            writing_mode: if let Some(writing_mode) = cli.hemingway {
-                match writing_mode {
-                    true => WritingMode::Hemingway,
-                    false => WritingMode::Regular,
-                }
-            } else {
-                default.writing_mode
+        let mut config = Config {
+            writing_mode: match (cli.blind, cli.hemingway) {
+                (Some(true), _) => WritingMode::Blind,
+                (_, Some(true)) => WritingMode::Hemingway,
+                (Some(false), _) | (_, Some(false)) => WritingMode::Regular,
+                (None, None) => default.writing_mode,
             },
+            blind_visible_words: cli.blind_words.or(default.blind_visible_words),
             output_name: cli.path.clone().unwrap(),
             output_dir: cli.directory.clone().unwrap_or(default.output_dir),
             use_autosave: cli.autosave.unwrap_or(default.use_autosave),
             autosave_interval: cli.autosave_interval.unwrap_or(default.autosave_interval),
-            show_timer: cli.timer.unwrap_or(default.show_timer),
+            show_timer,
+            timer_duration: cli
+                .duration
+                .map(|minutes| Duration::from_secs(u64::from(minutes) * 60))
+                .or(default.timer_duration),
+            word_goal: cli.goal.or(default.word_goal),
+            show_status_line: cli.status_line.unwrap_or(default.show_status_line),
+            status_template: cli.status.clone().unwrap_or_else(|| {
+                if show_timer {
+                    String::from(TIMER_STATUS_TEMPLATE)
+                } else {
+                    default.status_template
+                }
+            }),
+            pause_threshold: cli
+                .pause_threshold
+                .map(|secs| Duration::from_secs(secs.into()))
+                .unwrap_or(default.pause_threshold),
+            append_only: cli.append_only.unwrap_or(default.append_only),
+            append_separator: cli.separator.clone().unwrap_or(default.append_separator),
+            header: cli.header.clone().or(default.header),
+            prompt_file: get_prompt_file(cli),
+            prompt_tag: cli.prompt_tag.clone().or(default.prompt_tag),
+            prompt_placement: cli.prompt_placement.unwrap_or(default.prompt_placement),
+            large_file_threshold: match cli.large_file {
+                Some(kb) => Some(kb.checked_mul(1024).ok_or(ConfigError {
+                    error_type: ConfigErrorType::LargeFileTooLarge,
+                    path: cli.config.clone().unwrap_or_default(),
+                })?),
+                None => default.large_file_threshold,
+            },
+            tail_lines: cli.tail_lines.unwrap_or(default.tail_lines).max(1),
+            wrap_column: cli
+                .wrap
+                .filter(|&columns| columns > 0)
+                .or(default.wrap_column),
+            encrypt: cli.encrypt.unwrap_or(default.encrypt),
             use_hard_indent: cli.use_hard_indent.unwrap_or(default.use_hard_indent),
+            theme: Theme::load(
+                cli.theme.as_deref().unwrap_or(DEFAULT_THEME),
+                &cli.themes.clone().unwrap_or_default(),
+                ColorSupport::detect(),
+            )
+            .map_err(|err| ConfigError {
+                error_type: ConfigErrorType::InvalidTheme(err),
+                path: cli.config.clone().unwrap_or_default(),
+            })?,
+            history_path: get_history_path(cli),
+            snapshots: None,
+            record_path: cli.record.clone().or(default.record_path),
+            broadcast: match &cli.broadcast {
+                Some(addr) => Some(BroadcastAddr::parse(addr).map_err(|_| ConfigError {
+                    error_type: ConfigErrorType::InvalidBroadcastAddr,
+                    path: PathBuf::from(addr),
+                })?),
+                None => default.broadcast,
+            },
+            control_socket: cli.control.clone().or(default.control_socket),
+            git_commit: cli.git_commit.unwrap_or(default.git_commit),
+            git_commit_on_autosave: cli.git_autosave.unwrap_or(default.git_commit_on_autosave),
+            git_message: cli.git_message.clone().unwrap_or(default.git_message),
+            hooks: cli.hooks.clone().unwrap_or(default.hooks),
             ..default
         };
 
+        // Encrypted text must never reach the disk in plaintext
+        if let (true, Some(record_path)) = (config.encrypt, &config.record_path) {
+            return Err(ConfigError {
+                error_type: ConfigErrorType::UnencryptedWithEncrypt("record"),
+                path: record_path.clone(),
+            });
+        }
+        if config.encrypt && config.broadcast.is_some() {
+            return Err(ConfigError {
+                error_type: ConfigErrorType::UnencryptedWithEncrypt("broadcast"),
+                path: PathBuf::from(cli.broadcast.clone().unwrap_or_default()),
+            });
+        }
+        if config.hooks.timeout() > MAX_TIMEOUT {
+            return Err(ConfigError {
+                error_type: ConfigErrorType::HookTimeoutTooLong,
+                path: cli.config.clone().unwrap_or_default(),
+            });
+        }
+
+        // Snapshot directory is named after the output path, which is known only now
+        if cli.snapshots.unwrap_or(false) {
+            config.snapshots = Some(get_snapshots(cli, &config.get_output_path()).map_err(
+                |_| ConfigError {
+                    error_type: ConfigErrorType::InvalidSnapshotDir,
+                    path: get_snapshot_dir(cli),
+                },
+            )?);
+        }
+
         // Verify output directory exists and is writable
         let metadata = std::fs::metadata(&config.output_dir).map_err(|_| ConfigError {
             error_type: ConfigErrorType::FileReadError,
@@ -124,15 +364,19 @@ impl Config {
 
     /// Return path of backup file
     pub fn get_bak_path(&self) -> PathBuf {
-        let output_path = self.get_output_path();
-        let parent_dir = output_path.parent().unwrap();
-        let file_name = output_path.file_name().unwrap();
-        // to_string_lossy will drop invalid characters
-        let bak_file_name = file_name.to_string_lossy().to_string() + ".bak";
-        parent_dir.join(bak_file_name)
+        bak_path(&self.get_output_path())
     }
 }
 
+/// Return path of backup file of path
+pub fn bak_path(path: &Path) -> PathBuf {
+    let parent_dir = path.parent().unwrap();
+    let file_name = path.file_name().unwrap();
+    // to_string_lossy will drop invalid characters
+    let bak_file_name = file_name.to_string_lossy().to_string() + ".bak";
+    parent_dir.join(bak_file_name)
+}
+
 #[derive(Error, Debug)]
 pub enum ConfigErrorType {
     #[error("Invalid config path")]
@@ -149,6 +393,27 @@ pub enum ConfigErrorType {
 
     #[error("Output path is not writable")]
     OutputPathNotWritable,
+
+    #[error("Invalid theme in config: {0}")]
+    InvalidTheme(ThemeError),
+
+    #[error("Invalid snapshot directory")]
+    InvalidSnapshotDir,
+
+    #[error("Invalid broadcast address, TCP addresses must be local like 127.0.0.1:7878")]
+    InvalidBroadcastAddr,
+
+    #[error("Preset '{0}' is not defined in config")]
+    UnknownPreset(String),
+
+    #[error("--{0} would leave the text unencrypted, so it can't be used with --encrypt")]
+    UnencryptedWithEncrypt(&'static str),
+
+    #[error("Hook timeout can be at most {} seconds", MAX_TIMEOUT.as_secs())]
+    HookTimeoutTooLong,
+
+    #[error("Large file threshold can be at most {} KB", u64::MAX / 1024)]
+    LargeFileTooLarge,
 }
 
 #[derive(Debug)]
@@ -166,7 +431,12 @@ impl std::fmt::Display for ConfigError {
 impl std::error::Error for ConfigError {}
 
 /// Consume cli, check for user config files, merge them and return a Config
-pub fn load_user_config(mut cli: Cli) -> Result<Config, ConfigError> {
+pub fn load_user_config(cli: Cli) -> Result<Config, ConfigError> {
+    Config::new(&merge_user_config(cli)?)
+}
+
+/// Consume cli, check for user config file, and return cli with options from the file merged in
+pub fn merge_user_config(mut cli: Cli) -> Result<Cli, ConfigError> {
     let config_dir = config_dir().expect("Failed to get configuration directory");
     let default_config_path = config_dir.join("hemm").join("hemm.conf");
     let config_path = cli.config.clone().unwrap_or(default_config_path);
@@ -179,8 +449,15 @@ pub fn load_user_config(mut cli: Cli) -> Result<Config, ConfigError> {
                 path: config_path.clone(),
             });
         }
-        // Return config from cli options if no config file
-        return Ok(Config::new(&cli)?);
+        // Presets can only be defined in a config file
+        if let Some(preset) = cli.preset.clone() {
+            return Err(ConfigError {
+                error_type: ConfigErrorType::UnknownPreset(preset),
+                path: config_path,
+            });
+        }
+        // Return cli options as-is if no config file
+        return Ok(cli);
     }
 
     let mut config_file = File::open(&config_path).map_err(|_| ConfigError {
@@ -201,5 +478,68 @@ pub fn load_user_config(mut cli: Cli) -> Result<Config, ConfigError> {
     })?;
     cli.merge(config_cli);
 
-    Ok(Config::new(&cli)?)
+    // Options from the selected preset take precedence over the rest of the config file
+    if let Some(preset_name) = cli.preset.clone() {
+        let preset = cli
+            .presets
+            .as_mut()
+            .and_then(|presets| presets.remove(&preset_name))
+            .ok_or_else(|| ConfigError {
+                error_type: ConfigErrorType::UnknownPreset(preset_name),
+                path: config_path.clone(),
+            })?;
+        cli.merge(preset);
+    }
+    cli.config = Some(config_path);
+
+    Ok(cli)
+}
+
+/// Return path of history file, or None if history is disabled
+pub fn get_history_path(cli: &Cli) -> Option<PathBuf> {
+    if !cli.history.unwrap_or(true) {
+        return None;
+    }
+    cli.history_file.clone().or_else(|| {
+        let data_dir = data_dir().expect("Failed to get data directory");
+        Some(data_dir.join("hemm").join("history.jsonl"))
+    })
+}
+
+/// Return path of the file recording the chapter a project was left off at, named after the
+/// absolute path of its manifest
+pub fn get_project_state_path(manifest: &Path) -> io::Result<PathBuf> {
+    let manifest = manifest.canonicalize()?;
+    let file_name = manifest.to_string_lossy().replace(['/', '\\', ':'], "%") + ".json";
+    let data_dir = data_dir().expect("Failed to get data directory");
+    Ok(data_dir.join("hemm").join("projects").join(file_name))
+}
+
+/// Return path of the prompt file, or None if the session starts without a prompt
+pub fn get_prompt_file(cli: &Cli) -> Option<PathBuf> {
+    if !cli.prompt.unwrap_or(cli.prompt_tag.is_some()) {
+        return None;
+    }
+    cli.prompt_file.clone().or_else(|| {
+        let config_dir = config_dir().expect("Failed to get configuration directory");
+        Some(config_dir.join("hemm").join("prompts.txt"))
+    })
+}
+
+/// Return directory snapshots are stored in
+pub fn get_snapshot_dir(cli: &Cli) -> PathBuf {
+    cli.snapshot_dir.clone().unwrap_or_else(|| {
+        let data_dir = data_dir().expect("Failed to get data directory");
+        data_dir.join("hemm").join("snapshots")
+    })
+}
+
+/// Return snapshots of file, configured from cli
+pub fn get_snapshots(cli: &Cli, file: &Path) -> io::Result<Snapshots> {
+    Snapshots::new(
+        &get_snapshot_dir(cli),
+        file,
+        cli.snapshot_interval.unwrap_or(SnapshotInterval::Hour),
+        cli.snapshot_keep.unwrap_or(24),
+    )
 }
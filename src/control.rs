@@ -0,0 +1,308 @@
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::broadcast::remove_stale_socket;
+use crate::buffer::Buffer;
+use crate::config::Config;
+use crate::event::{AppEvent, EventSender, Shutdown};
+use crate::hooks::{HookContext, HookEvent, Hooks};
+use crate::input::type_text;
+
+/// How long to wait for connections and commands before checking if the thread should stop
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Command sent by a script to a running session, one JSON object per line, e.g.
+/// `{"command":"insert","text":"Chapter two\n"}`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum ControlCommand {
+    /// Type text at the cursor, as if typed by the user
+    Insert { text: String },
+    /// Reply with the number of words in the file
+    WordCount,
+    /// Save the file now
+    Save,
+    /// Show a message in the status line
+    Message { text: String },
+    /// Add minutes to the timer duration
+    ExtendTimer { minutes: u64 },
+    /// Save and exit, as if the user pressed Esc
+    Quit,
+}
+
+/// Reply to a command, one JSON object per line
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ControlResponse {
+    pub ok: bool,
+    /// Set for word_count
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub words: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ControlResponse {
+    fn ok() -> Self {
+        Self {
+            ok: true,
+            ..Default::default()
+        }
+    }
+
+    fn error(error: String) -> Self {
+        Self {
+            ok: false,
+            error: Some(error),
+            ..Default::default()
+        }
+    }
+}
+
+/// Applies commands to the session's buffer and notifies the render loop
+struct Controller {
+    buffer: Arc<Mutex<Buffer>>,
+    events: EventSender,
+    hemingway_mode: bool,
+    has_timer: bool,
+    hooks: Hooks,
+}
+
+impl Controller {
+    fn handle(&self, command: ControlCommand) -> ControlResponse {
+        match command {
+            ControlCommand::Insert { text } => {
+                type_text(&mut self.buffer.lock().unwrap(), &text, self.hemingway_mode);
+                _ = self.events.send(AppEvent::Input);
+            }
+            ControlCommand::WordCount => {
+                return ControlResponse {
+                    words: Some(self.buffer.lock().unwrap().word_count()),
+                    ..ControlResponse::ok()
+                };
+            }
+            ControlCommand::Save => {
+                // Written outside the lock, like autosave
+                let (pending, hook_context) = {
+                    let buffer = self.buffer.lock().unwrap();
+                    let hook_context = HookContext::new(&buffer);
+                    (buffer.pending_save(), hook_context)
+                };
+                if let Err(err) = pending.write() {
+                    _ = self
+                        .events
+                        .send(AppEvent::SaveResult(Err(String::from("Error when saving"))));
+                    self.hooks.spawn(
+                        HookEvent::SaveError,
+                        hook_context.with_error(&err),
+                        &self.events,
+                    );
+                    return ControlResponse::error(format!("Failed to save: {}", err));
+                }
+                self.buffer.lock().unwrap().mark_saved(&pending);
+                _ = self.events.send(AppEvent::SaveResult(Ok(())));
+                self.hooks
+                    .spawn(HookEvent::Save, hook_context, &self.events);
+            }
+            ControlCommand::Message { text } => {
+                _ = self.events.send(AppEvent::Message(text));
+            }
+            ControlCommand::ExtendTimer { minutes } => {
+                if !self.has_timer {
+                    return ControlResponse::error(String::from("No timer duration is set"));
+                }
+                let Some(secs) = minutes.checked_mul(60) else {
+                    return ControlResponse::error(format!("{} minutes is too long", minutes));
+                };
+                _ = self
+                    .events
+                    .send(AppEvent::ExtendTimer(Duration::from_secs(secs)));
+            }
+            ControlCommand::Quit => {
+                _ = self.events.send(AppEvent::Quit);
+            }
+        }
+        ControlResponse::ok()
+    }
+
+    /// Reply to commands from a script until it disconnects, or the thread should stop
+    fn serve(&self, stream: UnixStream, shutdown: &Shutdown) -> io::Result<()> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(POLL_INTERVAL))?;
+        let mut writer = stream.try_clone()?;
+        let mut reader = BufReader::new(stream);
+        // Kept across timeouts, which can happen in the middle of a line
+        let mut line = Vec::new();
+        while !shutdown.requested() {
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    continue
+                }
+                Err(err) => return Err(err),
+            }
+            if line.iter().all(u8::is_ascii_whitespace) {
+                line.clear();
+                continue;
+            }
+            let response = match serde_json::from_slice(&line) {
+                Ok(command) => self.handle(command),
+                Err(err) => ControlResponse::error(format!("Invalid command: {}", err)),
+            };
+            line.clear();
+            writeln!(writer, "{}", serde_json::to_string(&response)?)?;
+        }
+        Ok(())
+    }
+}
+
+/// Removes the socket file when the control thread stops
+struct SocketFile(PathBuf);
+
+impl Drop for SocketFile {
+    fn drop(&mut self) {
+        _ = fs::remove_file(&self.0);
+    }
+}
+
+/// Starts thread that accepts commands from scripts on a Unix socket at path.
+/// Scripts are served one at a time, in the order they connect
+pub fn start_control_thread(
+    path: &Path,
+    buffer: Arc<Mutex<Buffer>>,
+    events: EventSender,
+    shutdown: Shutdown,
+    config: &Config,
+) -> io::Result<JoinHandle<()>> {
+    remove_stale_socket(path)?;
+    let listener = UnixListener::bind(path)?;
+    listener.set_nonblocking(true)?;
+    let socket_file = SocketFile(path.to_path_buf());
+    let controller = Controller {
+        buffer,
+        events,
+        hemingway_mode: config.writing_mode.is_restricted(),
+        has_timer: config.timer_duration.is_some(),
+        hooks: config.hooks.clone(),
+    };
+    Ok(thread::spawn(move || {
+        let _socket_file = socket_file;
+        loop {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    // A script that fails only affects itself
+                    _ = controller.serve(stream, &shutdown);
+                }
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                    if shutdown.wait(POLL_INTERVAL) {
+                        break;
+                    }
+                }
+                Err(_) => break,
+            }
+            if shutdown.requested() {
+                break;
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Cli;
+    use crate::event::{event_bus, shutdown_channel};
+    use clap::Parser;
+
+    fn send(stream: &mut BufReader<UnixStream>, command: &str) -> ControlResponse {
+        writeln!(stream.get_mut(), "{}", command).unwrap();
+        let mut line = String::new();
+        stream.read_line(&mut line).unwrap();
+        serde_json::from_str(&line).unwrap()
+    }
+
+    #[test]
+    fn scripts_control_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pages.txt");
+        let socket_path = dir.path().join("hemm.sock");
+        let cli = Cli::parse_from(["hemm", path.to_str().unwrap(), "--duration", "10"]);
+        let config = Config::new(&cli).unwrap();
+        let buffer = Arc::new(Mutex::new(Buffer::new(&config).unwrap()));
+        let (events, event_receiver) = event_bus();
+        let (signal, shutdown) = shutdown_channel();
+        let handle =
+            start_control_thread(&socket_path, Arc::clone(&buffer), events, shutdown, &config)
+                .unwrap();
+
+        let mut stream = BufReader::new(UnixStream::connect(&socket_path).unwrap());
+        let inserted = send(
+            &mut stream,
+            r#"{"command":"insert","text":"Morning pages\n"}"#,
+        );
+        assert_eq!(inserted, ControlResponse::ok());
+        let count = send(&mut stream, r#"{"command":"word_count"}"#);
+        assert_eq!(count.words, Some(2));
+        assert!(send(&mut stream, r#"{"command":"save"}"#).ok);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "Morning pages\n\n");
+        assert!(send(&mut stream, r#"{"command":"extend_timer","minutes":5}"#).ok);
+        let too_long = r#"{"command":"extend_timer","minutes":18446744073709551615}"#;
+        assert!(!send(&mut stream, too_long).ok);
+        assert!(!send(&mut stream, r#"{"command":"dance"}"#).ok);
+        assert!(send(&mut stream, r#"{"command":"quit"}"#).ok);
+
+        let events: Vec<_> = event_receiver.try_iter().collect();
+        assert!(matches!(
+            events.as_slice(),
+            [
+                AppEvent::Input,
+                AppEvent::SaveResult(Ok(())),
+                AppEvent::ExtendTimer(extension),
+                AppEvent::Quit
+            ] if *extension == Duration::from_secs(300)
+        ));
+
+        drop(stream);
+        drop(signal);
+        handle.join().unwrap();
+        assert!(!socket_path.exists());
+    }
+
+    #[test]
+    fn replaces_only_sockets() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pages.txt");
+        let socket_path = dir.path().join("hemm.sock");
+        let config = Config::new(&Cli::parse_from(["hemm", path.to_str().unwrap()])).unwrap();
+        let buffer = Arc::new(Mutex::new(Buffer::new(&config).unwrap()));
+        let start = || {
+            let (events, _) = event_bus();
+            let (signal, shutdown) = shutdown_channel();
+            start_control_thread(&socket_path, Arc::clone(&buffer), events, shutdown, &config).map(
+                |handle| {
+                    drop(signal);
+                    handle.join().unwrap();
+                },
+            )
+        };
+        // Left behind by a session that crashed
+        drop(UnixListener::bind(&socket_path).unwrap());
+        start().unwrap();
+
+        fs::write(&socket_path, "Morning pages").unwrap();
+        assert_eq!(start().unwrap_err().kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read_to_string(&socket_path).unwrap(), "Morning pages");
+    }
+}
@@ -0,0 +1,154 @@
+use std::io;
+
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use thiserror::Error;
+
+// Encrypted file format, version 1:
+//
+// | magic (8 bytes) | salt (16 bytes) | nonce (24 bytes) | ciphertext and tag |
+//
+// The key is derived from the passphrase and salt with Argon2id, using the default parameters of
+// the argon2 crate. The contents are encrypted with XChaCha20-Poly1305, with a new random nonce
+// on every save.
+
+/// Identifies an encrypted hemm file, and the version of the format
+const MAGIC: &[u8; 8] = b"HEMMENC1";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const HEADER_LEN: usize = MAGIC.len() + SALT_LEN + NONCE_LEN;
+
+#[derive(Error, Debug)]
+pub enum CryptoError {
+    #[error("File is not in the hemm encrypted format")]
+    InvalidFormat,
+
+    #[error("Wrong passphrase, or file is corrupted")]
+    DecryptionFailed,
+
+    #[error("Failed to encrypt")]
+    EncryptionFailed,
+
+    #[error("Failed to derive key from passphrase")]
+    KeyDerivationFailed,
+}
+
+impl From<CryptoError> for io::Error {
+    fn from(err: CryptoError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+/// Whether data is in the hemm encrypted format
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// Encrypts and decrypts file contents with a key derived from a passphrase
+pub struct Cipher {
+    cipher: XChaCha20Poly1305,
+    salt: [u8; SALT_LEN],
+}
+
+impl Cipher {
+    /// Cipher for a new file, with a new random salt
+    pub fn new(passphrase: &str) -> Result<Self, CryptoError> {
+        let mut salt = [0; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        Self::with_salt(passphrase, salt)
+    }
+
+    /// Cipher for an existing encrypted file, using the salt stored in it
+    pub fn for_encrypted(passphrase: &str, data: &[u8]) -> Result<Self, CryptoError> {
+        if !is_encrypted(data) || data.len() < HEADER_LEN {
+            return Err(CryptoError::InvalidFormat);
+        }
+        let mut salt = [0; SALT_LEN];
+        salt.copy_from_slice(&data[MAGIC.len()..MAGIC.len() + SALT_LEN]);
+        Self::with_salt(passphrase, salt)
+    }
+
+    fn with_salt(passphrase: &str, salt: [u8; SALT_LEN]) -> Result<Self, CryptoError> {
+        let mut key = [0; 32];
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+            .map_err(|_| CryptoError::KeyDerivationFailed)?;
+        Ok(Self {
+            cipher: XChaCha20Poly1305::new(Key::from_slice(&key)),
+            salt,
+        })
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| CryptoError::EncryptionFailed)?;
+        let mut data = Vec::with_capacity(HEADER_LEN + ciphertext.len());
+        data.extend_from_slice(MAGIC);
+        data.extend_from_slice(&self.salt);
+        data.extend_from_slice(&nonce);
+        data.extend_from_slice(&ciphertext);
+        Ok(data)
+    }
+
+    pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        if !is_encrypted(data) || data.len() < HEADER_LEN {
+            return Err(CryptoError::InvalidFormat);
+        }
+        let nonce = XNonce::from_slice(&data[MAGIC.len() + SALT_LEN..HEADER_LEN]);
+        self.cipher
+            .decrypt(nonce, &data[HEADER_LEN..])
+            .map_err(|_| CryptoError::DecryptionFailed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_and_rejects_wrong_passphrase() {
+        let cipher = Cipher::new("correct horse").unwrap();
+        let data = cipher.encrypt(b"Dear diary").unwrap();
+        assert!(is_encrypted(&data));
+        assert!(!data.windows(5).any(|window| window == b"diary"));
+        // A new nonce on every save
+        assert_ne!(cipher.encrypt(b"Dear diary").unwrap(), data);
+
+        let reopened = Cipher::for_encrypted("correct horse", &data).unwrap();
+        assert_eq!(reopened.decrypt(&data).unwrap(), b"Dear diary");
+
+        let wrong = Cipher::for_encrypted("battery staple", &data).unwrap();
+        assert!(matches!(
+            wrong.decrypt(&data),
+            Err(CryptoError::DecryptionFailed)
+        ));
+    }
+
+    #[test]
+    fn rejects_tampered_and_plain_data() {
+        let cipher = Cipher::new("correct horse").unwrap();
+        let mut data = cipher.encrypt(b"Dear diary").unwrap();
+        let last = data.len() - 1;
+        data[last] ^= 1;
+        assert!(matches!(
+            cipher.decrypt(&data),
+            Err(CryptoError::DecryptionFailed)
+        ));
+
+        assert!(!is_encrypted(b"Dear diary"));
+        assert!(!is_encrypted(b""));
+        assert!(matches!(
+            cipher.decrypt(b"Dear diary"),
+            Err(CryptoError::InvalidFormat)
+        ));
+        assert!(matches!(
+            Cipher::for_encrypted("correct horse", MAGIC),
+            Err(CryptoError::InvalidFormat)
+        ));
+    }
+}
@@ -0,0 +1,66 @@
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::time::Duration;
+
+/// Events sent to the render loop by the other threads.
+/// The render loop sleeps until an event arrives, and redraws after handling it
+#[derive(Debug)]
+pub enum AppEvent {
+    /// A key was pressed or text was inserted, and has already been applied to the buffer
+    Input,
+    /// The terminal was resized
+    Resize,
+    /// Time elapsed since the session started, sent once per second
+    Tick(Duration),
+    /// Outcome of an autosave. Errors hold a message for the user
+    SaveResult(Result<(), String>),
+    /// Message for the user, e.g. sent by a script over the control socket
+    Message(String),
+    /// Add time to the timer duration
+    ExtendTimer(Duration),
+    /// Open the chapter this many chapters after the current one, or before it if negative
+    SwitchChapter(isize),
+    /// The user asked to exit
+    Quit,
+}
+
+/// Sending end of the event bus. Each producer thread holds a clone
+pub type EventSender = Sender<AppEvent>;
+
+/// Receiving end of the event bus, held by the render loop
+pub type EventReceiver = Receiver<AppEvent>;
+
+pub fn event_bus() -> (EventSender, EventReceiver) {
+    mpsc::channel()
+}
+
+/// Stops a thread when dropped
+pub struct ShutdownSignal {
+    _sender: Sender<()>,
+}
+
+/// Held by a thread to know when to stop
+pub struct Shutdown {
+    receiver: Receiver<()>,
+}
+
+/// Create a shutdown signal for one thread
+pub fn shutdown_channel() -> (ShutdownSignal, Shutdown) {
+    let (sender, receiver) = mpsc::channel();
+    (ShutdownSignal { _sender: sender }, Shutdown { receiver })
+}
+
+impl Shutdown {
+    /// Sleep for up to `timeout`, waking up as soon as the thread should stop.
+    /// Returns true if the thread should stop
+    pub fn wait(&self, timeout: Duration) -> bool {
+        !matches!(
+            self.receiver.recv_timeout(timeout),
+            Err(RecvTimeoutError::Timeout)
+        )
+    }
+
+    /// Whether the thread should stop, without waiting
+    pub fn requested(&self) -> bool {
+        !matches!(self.receiver.try_recv(), Err(TryRecvError::Empty))
+    }
+}
@@ -0,0 +1,407 @@
+use std::fmt::Write;
+
+use chrono::{SecondsFormat, Utc};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::wrap::wrap_paragraph;
+use crate::zip::ZipWriter;
+
+/// Width of lines in plain text exports
+const TEXT_WIDTH: usize = 72;
+
+/// Format a file is exported to
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExportFormat {
+    /// Plain text wrapped at 72 columns, ready to print
+    Txt,
+    /// Markdown
+    #[value(alias = "md")]
+    Markdown,
+    /// Standalone HTML page
+    Html,
+    /// EPUB 3 e-book
+    Epub,
+    /// Word document with plain paragraphs, without styles
+    DocxLite,
+}
+
+impl ExportFormat {
+    /// File extension of the format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Txt => "txt",
+            ExportFormat::Markdown => "md",
+            ExportFormat::Html => "html",
+            ExportFormat::Epub => "epub",
+            ExportFormat::DocxLite => "docx",
+        }
+    }
+}
+
+/// Details about the document added to exports
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Metadata {
+    pub title: Option<String>,
+    pub author: Option<String>,
+}
+
+/// Split text into paragraphs, which are separated by blank lines.
+/// Line breaks within paragraphs are kept, and so is trailing whitespace, a hard break in Markdown
+pub fn paragraphs(text: &str) -> Vec<String> {
+    let mut paragraphs = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    for line in text.lines() {
+        if line.trim().is_empty() {
+            if !paragraph.is_empty() {
+                paragraphs.push(paragraph.join("\n"));
+                paragraph.clear();
+            }
+        } else {
+            paragraph.push(line);
+        }
+    }
+    if !paragraph.is_empty() {
+        paragraphs.push(paragraph.join("\n"));
+    }
+    paragraphs
+}
+
+/// Export text to format
+pub fn export(text: &str, format: ExportFormat, metadata: &Metadata) -> Vec<u8> {
+    let paragraphs = paragraphs(text);
+    match format {
+        ExportFormat::Txt => to_txt(&paragraphs, metadata).into_bytes(),
+        ExportFormat::Markdown => to_markdown(&paragraphs, metadata).into_bytes(),
+        ExportFormat::Html => to_html(&paragraphs, metadata).into_bytes(),
+        ExportFormat::Epub => to_epub(&paragraphs, metadata),
+        ExportFormat::DocxLite => to_docx(&paragraphs, metadata),
+    }
+}
+
+fn to_txt(paragraphs: &[String], metadata: &Metadata) -> String {
+    let mut blocks = Vec::new();
+    if let Some(title) = &metadata.title {
+        let mut heading = wrap_paragraph(title, TEXT_WIDTH).join("\n");
+        if let Some(author) = &metadata.author {
+            heading = heading + "\n" + author;
+        }
+        blocks.push(heading);
+    }
+    blocks.extend(
+        paragraphs
+            .iter()
+            .map(|paragraph| wrap_paragraph(&join_lines(paragraph), TEXT_WIDTH).join("\n")),
+    );
+    blocks.join("\n\n") + "\n"
+}
+
+fn to_markdown(paragraphs: &[String], metadata: &Metadata) -> String {
+    let mut blocks = Vec::new();
+    if let Some(title) = &metadata.title {
+        blocks.push(format!("# {}", title));
+    }
+    if let Some(author) = &metadata.author {
+        blocks.push(format!("*{}*", author));
+    }
+    blocks.extend(paragraphs.iter().cloned());
+    blocks.join("\n\n") + "\n"
+}
+
+/// Escape text for HTML and XML
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped += "&amp;",
+            '<' => escaped += "&lt;",
+            '>' => escaped += "&gt;",
+            '"' => escaped += "&quot;",
+            '\'' => escaped += "&apos;",
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Lines of a paragraph as one line. Lines are wrapped by the reader in HTML and DOCX,
+/// where a line break would show as a break in the middle of the paragraph
+fn join_lines(paragraph: &str) -> String {
+    paragraph
+        .lines()
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Body of an HTML or XHTML document: title, author and paragraphs
+fn html_body(paragraphs: &[String], metadata: &Metadata) -> String {
+    let mut body = String::new();
+    // Writing to String cannot fail
+    if let Some(title) = &metadata.title {
+        let _ = writeln!(body, "<h1>{}</h1>", escape(title));
+    }
+    if let Some(author) = &metadata.author {
+        let _ = writeln!(body, "<p class=\"author\">{}</p>", escape(author));
+    }
+    for paragraph in paragraphs {
+        let _ = writeln!(body, "<p>{}</p>", escape(&join_lines(paragraph)));
+    }
+    body
+}
+
+fn to_html(paragraphs: &[String], metadata: &Metadata) -> String {
+    let mut html = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    if let Some(title) = &metadata.title {
+        let _ = writeln!(html, "<title>{}</title>", escape(title));
+    }
+    if let Some(author) = &metadata.author {
+        let _ = writeln!(
+            html,
+            "<meta name=\"author\" content=\"{}\">",
+            escape(author)
+        );
+    }
+    html += "</head>\n<body>\n";
+    html += &html_body(paragraphs, metadata);
+    html += "</body>\n</html>\n";
+    html
+}
+
+fn to_epub(paragraphs: &[String], metadata: &Metadata) -> Vec<u8> {
+    let title = escape(metadata.title.as_deref().unwrap_or("Untitled"));
+    let modified = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+    let creator = metadata
+        .author
+        .as_ref()
+        .map(|author| format!("    <dc:creator>{}</dc:creator>\n", escape(author)))
+        .unwrap_or_default();
+
+    let container = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+    let package = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="id">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="id">urn:hemm:{modified}</dc:identifier>
+    <dc:title>{title}</dc:title>
+{creator}    <dc:language>en</dc:language>
+    <meta property="dcterms:modified">{modified}</meta>
+  </metadata>
+  <manifest>
+    <item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+    <item id="text" href="text.xhtml" media-type="application/xhtml+xml"/>
+  </manifest>
+  <spine>
+    <itemref idref="text"/>
+  </spine>
+</package>
+"#
+    );
+    let nav = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+<head><title>{title}</title></head>
+<body>
+<nav epub:type="toc"><ol><li><a href="text.xhtml">{title}</a></li></ol></nav>
+</body>
+</html>
+"#
+    );
+    let text = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml">
+<head><title>{title}</title></head>
+<body>
+{}</body>
+</html>
+"#,
+        html_body(paragraphs, metadata)
+    );
+
+    let mut zip = ZipWriter::new();
+    // Must be the first file, so readers can recognize the format
+    zip.add("mimetype", b"application/epub+zip");
+    zip.add("META-INF/container.xml", container.as_bytes());
+    zip.add("OEBPS/content.opf", package.as_bytes());
+    zip.add("OEBPS/nav.xhtml", nav.as_bytes());
+    zip.add("OEBPS/text.xhtml", text.as_bytes());
+    zip.finish()
+}
+
+/// Paragraph of a Word document, with its lines joined into one
+fn docx_paragraph(text: &str, run_properties: &str) -> String {
+    format!(
+        "<w:p><w:r>{}<w:t xml:space=\"preserve\">{}</w:t></w:r></w:p>",
+        run_properties,
+        escape(&join_lines(text))
+    )
+}
+
+fn to_docx(paragraphs: &[String], metadata: &Metadata) -> Vec<u8> {
+    let mut body = String::new();
+    if let Some(title) = &metadata.title {
+        body += &docx_paragraph(title, "<w:rPr><w:b/><w:sz w:val=\"48\"/></w:rPr>");
+    }
+    if let Some(author) = &metadata.author {
+        body += &docx_paragraph(author, "<w:rPr><w:i/></w:rPr>");
+    }
+    for paragraph in paragraphs {
+        body += &docx_paragraph(paragraph, "");
+    }
+
+    let content_types = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">
+  <Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>
+  <Default Extension="xml" ContentType="application/xml"/>
+  <Override PartName="/word/document.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml"/>
+  <Override PartName="/docProps/core.xml" ContentType="application/vnd.openxmlformats-package.core-properties+xml"/>
+</Types>
+"#;
+    let relationships = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+  <Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="word/document.xml"/>
+  <Relationship Id="rId2" Type="http://schemas.openxmlformats.org/package/2006/relationships/metadata/core-properties" Target="docProps/core.xml"/>
+</Relationships>
+"#;
+    let core = format!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<cp:coreProperties xmlns:cp="http://schemas.openxmlformats.org/package/2006/metadata/core-properties" xmlns:dc="http://purl.org/dc/elements/1.1/">
+  <dc:title>{}</dc:title>
+  <dc:creator>{}</dc:creator>
+</cp:coreProperties>
+"#,
+        escape(metadata.title.as_deref().unwrap_or_default()),
+        escape(metadata.author.as_deref().unwrap_or_default())
+    );
+    let document = format!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main">
+<w:body>{}</w:body>
+</w:document>
+"#,
+        body
+    );
+
+    let mut zip = ZipWriter::new();
+    zip.add("[Content_Types].xml", content_types.as_bytes());
+    zip.add("_rels/.rels", relationships.as_bytes());
+    zip.add("docProps/core.xml", core.as_bytes());
+    zip.add("word/document.xml", document.as_bytes());
+    zip.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata() -> Metadata {
+        Metadata {
+            title: Some(String::from("Rain & Wind")),
+            author: Some(String::from("A. Writer")),
+        }
+    }
+
+    #[test]
+    fn splits_paragraphs_on_blank_lines() {
+        let text = "\nFirst line\nsecond line\n\n  \n\nNext <paragraph>\n";
+        assert_eq!(
+            paragraphs(text),
+            ["First line\nsecond line", "Next <paragraph>"]
+        );
+        let html = String::from_utf8(export(text, ExportFormat::Html, &metadata())).unwrap();
+        assert!(html.contains("<title>Rain &amp; Wind</title>"));
+        assert!(html.contains("<p>First line second line</p>\n<p>Next &lt;paragraph&gt;</p>"));
+    }
+
+    #[test]
+    fn keeps_markdown_hard_breaks() {
+        let text = "Roses are red,  \nviolets are blue.  \n\nNext  \n";
+        let markdown =
+            String::from_utf8(export(text, ExportFormat::Markdown, &Metadata::default())).unwrap();
+        assert_eq!(
+            markdown,
+            "Roses are red,  \nviolets are blue.  \n\nNext  \n"
+        );
+        let html =
+            String::from_utf8(export(text, ExportFormat::Html, &Metadata::default())).unwrap();
+        assert!(html.contains("<p>Roses are red, violets are blue.</p>\n<p>Next</p>"));
+    }
+
+    #[test]
+    fn wraps_plain_text() {
+        let text = "The rain fell on the roofs of the town all night, and in the morning the streets were rivers.";
+        let txt = String::from_utf8(export(text, ExportFormat::Txt, &Metadata::default())).unwrap();
+        assert_eq!(
+            txt,
+            "The rain fell on the roofs of the town all night, and in the morning the\nstreets were rivers.\n"
+        );
+    }
+
+    /// Names and contents of the files in a ZIP archive, which are stored uncompressed
+    fn unzip(data: &[u8]) -> Vec<(String, Vec<u8>)> {
+        let field = |offset: usize, len: usize| {
+            data[offset..offset + len]
+                .iter()
+                .rev()
+                .fold(0, |value, &byte| value << 8 | usize::from(byte))
+        };
+        let mut files = Vec::new();
+        let mut offset = 0;
+        // Local file headers, until the central directory
+        while field(offset, 4) == 0x0403_4b50 {
+            let size = field(offset + 18, 4);
+            let name_len = field(offset + 26, 2);
+            let start = offset + 30 + field(offset + 28, 2) + name_len;
+            let name = String::from_utf8(data[offset + 30..offset + 30 + name_len].to_vec());
+            files.push((name.unwrap(), data[start..start + size].to_vec()));
+            offset = start + size;
+        }
+        files
+    }
+
+    #[test]
+    fn docx_holds_paragraphs_as_single_lines() {
+        let docx = export(
+            "First line\nsecond line\n\nNext",
+            ExportFormat::DocxLite,
+            &metadata(),
+        );
+        let files = unzip(&docx);
+        let names: Vec<&str> = files.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "[Content_Types].xml",
+                "_rels/.rels",
+                "docProps/core.xml",
+                "word/document.xml"
+            ]
+        );
+        let document = String::from_utf8(files[3].1.clone()).unwrap();
+        assert!(document.contains(concat!(
+            "<w:p><w:r><w:t xml:space=\"preserve\">First line second line</w:t></w:r></w:p>",
+            "<w:p><w:r><w:t xml:space=\"preserve\">Next</w:t></w:r></w:p>"
+        )));
+        assert!(
+            document.contains("<w:rPr><w:i/></w:rPr><w:t xml:space=\"preserve\">A. Writer</w:t>")
+        );
+        assert!(!document.contains("<w:br/>"));
+    }
+
+    #[test]
+    fn epub_starts_with_mimetype() {
+        let epub = export("Once", ExportFormat::Epub, &metadata());
+        // Readers identify EPUB files by the uncompressed mimetype file at a fixed offset
+        assert_eq!(&epub[30..38], b"mimetype");
+        assert_eq!(&epub[38..58], b"application/epub+zip");
+    }
+}
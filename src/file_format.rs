@@ -0,0 +1,264 @@
+use std::io;
+
+use thiserror::Error;
+
+/// Line ending used when writing lines back to the file
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+/// Text encoding of the file
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encoding {
+    Utf8,
+    /// UTF-8 with a byte order mark
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    /// Fallback for files that are not valid UTF-8. Every byte maps to one character,
+    /// so any file round-trips unchanged
+    Latin1,
+}
+
+/// A character that can't be written in the encoding of the file
+#[derive(Error, Debug, PartialEq)]
+#[error("'{0}' can't be saved in the Latin-1 encoding of this file")]
+pub struct UnencodableChar(pub char);
+
+impl From<UnencodableChar> for io::Error {
+    fn from(err: UnencodableChar) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+impl UnencodableChar {
+    /// Whether err was caused by a character the file can't hold
+    pub fn caused(err: &io::Error) -> bool {
+        err.get_ref()
+            .map_or(false, |inner| inner.is::<UnencodableChar>())
+    }
+}
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16_LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16_BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+/// How the file was laid out on disk, so it can be written back the same way
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FileFormat {
+    pub line_ending: LineEnding,
+    /// Whether the last line ends with a line ending
+    pub final_newline: bool,
+    pub encoding: Encoding,
+}
+
+impl Default for FileFormat {
+    /// Format of new files
+    fn default() -> Self {
+        Self {
+            line_ending: LineEnding::Lf,
+            final_newline: true,
+            encoding: Encoding::Utf8,
+        }
+    }
+}
+
+fn decode_utf16(data: &[u8], from_bytes: fn([u8; 2]) -> u16) -> Option<String> {
+    if data.len() % 2 != 0 {
+        return None;
+    }
+    let units = data
+        .chunks_exact(2)
+        .map(|pair| from_bytes([pair[0], pair[1]]));
+    char::decode_utf16(units)
+        .collect::<Result<String, _>>()
+        .ok()
+}
+
+impl FileFormat {
+    /// Detect format of file contents and decode them.
+    /// Empty files get the format of new files
+    pub fn decode(data: &[u8]) -> (String, FileFormat) {
+        let mut format = FileFormat::default();
+        let text = if let Some(rest) = data.strip_prefix(UTF8_BOM) {
+            format.encoding = Encoding::Utf8Bom;
+            String::from_utf8_lossy(rest).into_owned()
+        } else if let Some(text) = data
+            .strip_prefix(UTF16_LE_BOM)
+            .and_then(|rest| decode_utf16(rest, u16::from_le_bytes))
+        {
+            format.encoding = Encoding::Utf16Le;
+            text
+        } else if let Some(text) = data
+            .strip_prefix(UTF16_BE_BOM)
+            .and_then(|rest| decode_utf16(rest, u16::from_be_bytes))
+        {
+            format.encoding = Encoding::Utf16Be;
+            text
+        } else {
+            match String::from_utf8(data.to_vec()) {
+                Ok(text) => text,
+                Err(_) => {
+                    format.encoding = Encoding::Latin1;
+                    data.iter().map(|&byte| byte as char).collect()
+                }
+            }
+        };
+
+        if text.is_empty() {
+            return (text, format);
+        }
+        // The first line ending found decides the style of the whole file
+        if let Some(index) = text.find('\n') {
+            if text[..index].ends_with('\r') {
+                format.line_ending = LineEnding::CrLf;
+            }
+        }
+        format.final_newline = text.ends_with('\n');
+        (text, format)
+    }
+
+    /// Join lines with the line ending of the file, ending with a line ending if the file did
+    pub fn join_lines(&self, lines: &[String]) -> String {
+        let mut text = lines.join(self.line_ending.as_str());
+        if self.final_newline {
+            text += self.line_ending.as_str();
+        }
+        text
+    }
+
+    /// Convert `\n` in text to the line ending of the file
+    pub fn convert_line_endings(&self, text: &str) -> String {
+        match self.line_ending {
+            LineEnding::Lf => text.to_string(),
+            LineEnding::CrLf => text.replace("\r\n", "\n").replace('\n', "\r\n"),
+        }
+    }
+
+    /// Encode text in the encoding of the file.
+    /// `with_bom` should be false when appending to an existing file.
+    /// Fails on characters that can't be encoded in Latin-1, rather than losing them
+    pub fn encode(&self, text: &str, with_bom: bool) -> Result<Vec<u8>, UnencodableChar> {
+        let mut data = Vec::new();
+        match self.encoding {
+            Encoding::Utf8 => data.extend_from_slice(text.as_bytes()),
+            Encoding::Utf8Bom => {
+                if with_bom {
+                    data.extend_from_slice(UTF8_BOM);
+                }
+                data.extend_from_slice(text.as_bytes());
+            }
+            Encoding::Utf16Le => {
+                if with_bom {
+                    data.extend_from_slice(UTF16_LE_BOM);
+                }
+                for unit in text.encode_utf16() {
+                    data.extend_from_slice(&unit.to_le_bytes());
+                }
+            }
+            Encoding::Utf16Be => {
+                if with_bom {
+                    data.extend_from_slice(UTF16_BE_BOM);
+                }
+                for unit in text.encode_utf16() {
+                    data.extend_from_slice(&unit.to_be_bytes());
+                }
+            }
+            Encoding::Latin1 => {
+                for c in text.chars() {
+                    data.push(u8::try_from(c).map_err(|_| UnencodableChar(c))?);
+                }
+            }
+        }
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decode data the way Buffer does, then write it back unchanged
+    fn round_trip(data: &[u8]) -> Vec<u8> {
+        let (text, format) = FileFormat::decode(data);
+        let lines: Vec<String> = text.lines().map(String::from).collect();
+        format.encode(&format.join_lines(&lines), true).unwrap()
+    }
+
+    #[test]
+    fn detects_line_endings_and_final_newline() {
+        let (_, format) = FileFormat::decode(b"one\r\ntwo");
+        assert_eq!(format.line_ending, LineEnding::CrLf);
+        assert!(!format.final_newline);
+
+        let (_, format) = FileFormat::decode(b"one\ntwo\n");
+        assert_eq!(format.line_ending, LineEnding::Lf);
+        assert!(format.final_newline);
+    }
+
+    #[test]
+    fn detects_encodings() {
+        assert_eq!(
+            FileFormat::decode(b"\xEF\xBB\xBFhi").1.encoding,
+            Encoding::Utf8Bom
+        );
+        assert_eq!(
+            FileFormat::decode(b"\xFF\xFEh\0i\0").1.encoding,
+            Encoding::Utf16Le
+        );
+        assert_eq!(
+            FileFormat::decode(b"\xFE\xFF\0h\0i").1.encoding,
+            Encoding::Utf16Be
+        );
+        assert_eq!(FileFormat::decode(b"caf\xE9").1.encoding, Encoding::Latin1);
+        assert_eq!(
+            FileFormat::decode(b"caf\xC3\xA9").1.encoding,
+            Encoding::Utf8
+        );
+    }
+
+    #[test]
+    fn round_trips_untouched_files() {
+        let files: &[&[u8]] = &[
+            b"one\ntwo\n",
+            b"one\ntwo",
+            b"one\r\ntwo\r\n",
+            b"one\r\ntwo",
+            b"one\n\n\ntwo\n\n",
+            b"\n",
+            b"\xEF\xBB\xBFone\r\ntwo\r\n",
+            b"\xFF\xFEo\0n\0e\0\r\0\n\0",
+            b"\xFE\xFF\0o\0n\0e\0\n",
+            b"caf\xE9\nna\xEFve",
+        ];
+        for &data in files {
+            assert_eq!(
+                round_trip(data),
+                data,
+                "{:?}",
+                String::from_utf8_lossy(data)
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_characters_latin1_cannot_encode() {
+        let format = FileFormat {
+            encoding: Encoding::Latin1,
+            ..FileFormat::default()
+        };
+        assert_eq!(format.encode("café", true).unwrap(), b"caf\xE9");
+        assert_eq!(format.encode("café — ✓", true), Err(UnencodableChar('—')));
+    }
+}
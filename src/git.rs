@@ -0,0 +1,133 @@
+use std::io;
+use std::path::Path;
+use std::process::{Command, Output};
+
+use chrono::{DateTime, Local};
+
+use crate::template::{datetime_field, render_template};
+
+/// Default template of commit messages
+pub const DEFAULT_COMMIT_MESSAGE: &str = "Update {file} ({words} words)";
+
+/// Fill in commit message template.
+/// Fields: file, words, session_words, date, time, datetime
+pub fn commit_message(
+    template: &str,
+    file: &Path,
+    words: usize,
+    session_words: usize,
+    now: &DateTime<Local>,
+) -> String {
+    render_template(template, |name| match name {
+        "file" => file
+            .file_name()
+            .map(|file_name| file_name.to_string_lossy().to_string()),
+        "words" => Some(words.to_string()),
+        "session_words" => Some(session_words.to_string()),
+        _ => datetime_field(name, now),
+    })
+}
+
+/// Run git in directory, returning an error with git's output if it fails
+fn git(dir: &Path, args: &[&str]) -> io::Result<Output> {
+    let output = Command::new("git").arg("-C").arg(dir).args(args).output()?;
+    if !output.status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!(
+                "git {} failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        ));
+    }
+    Ok(output)
+}
+
+/// Commit file to the git repository containing it, using the local git binary.
+/// Only this file is committed, even if other changes are staged.
+/// Returns false if the file had no changes to commit
+pub fn commit_file(file: &Path, message: &str) -> io::Result<bool> {
+    let dir = match file.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let file_name = file
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Path is not a file"))?
+        .to_string_lossy();
+
+    git(dir, &["add", "--", &file_name])?;
+    // Exit code 0 means no staged changes to the file
+    let unchanged = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["diff", "--cached", "--quiet", "--", &file_name])
+        .status()?
+        .success();
+    if unchanged {
+        return Ok(false);
+    }
+    git(dir, &["commit", "--quiet", "-m", message, "--", &file_name])?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use chrono::TimeZone;
+
+    use super::*;
+
+    /// Temporary repository with an identity, so commits work without a global git config
+    fn repository() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for args in [
+            &["init", "--quiet"][..],
+            &["config", "user.name", "Writer"],
+            &["config", "user.email", "writer@example.com"],
+            &["config", "commit.gpgsign", "false"],
+        ] {
+            git(dir.path(), args).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn commits_file_with_message() {
+        let dir = repository();
+        let file = dir.path().join("notes.txt");
+        fs::write(&file, "one two three\n").unwrap();
+        let now = Local.with_ymd_and_hms(2024, 3, 6, 7, 30, 0).unwrap();
+        let message = commit_message(
+            "{file}: {words} words, {session_words} new on {date}",
+            &file,
+            3,
+            2,
+            &now,
+        );
+        assert_eq!(message, "notes.txt: 3 words, 2 new on 2024-03-06");
+        // Other staged changes are left out of the commit
+        fs::write(dir.path().join("other.txt"), "other").unwrap();
+        git(dir.path(), &["add", "other.txt"]).unwrap();
+
+        assert!(commit_file(&file, &message).unwrap());
+        let log = git(dir.path(), &["log", "--format=%s", "--name-only"]).unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&log.stdout).trim(),
+            "notes.txt: 3 words, 2 new on 2024-03-06\n\nnotes.txt"
+        );
+
+        // Nothing changed since
+        assert!(!commit_file(&file, &message).unwrap());
+    }
+
+    #[test]
+    fn fails_outside_repository() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("notes.txt");
+        fs::write(&file, "text").unwrap();
+        assert!(commit_file(&file, "Update").is_err());
+    }
+}
@@ -0,0 +1,405 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate};
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::status::format_duration;
+
+/// A finished writing session, stored as one JSON line in the history file
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SessionRecord {
+    /// When the session ended
+    pub date: DateTime<Local>,
+    /// File that was written
+    pub file: PathBuf,
+    /// Length of the session, in seconds
+    pub duration_secs: u64,
+    /// Words written during the session
+    pub words: usize,
+    /// Writing mode used
+    pub mode: String,
+    /// Whether the word goal was met, None if there was no goal
+    pub goal_met: Option<bool>,
+    /// Writing prompt the session started with, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+}
+
+/// Append session to history file, creating the file and its directory if needed
+pub fn append_record(path: &Path, record: &SessionRecord) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut line = serde_json::to_string(record)?;
+    line.push('\n');
+    let mut f = OpenOptions::new().create(true).append(true).open(path)?;
+    f.write_all(line.as_bytes())
+}
+
+/// Read all sessions from history file.
+/// Lines that fail to parse are skipped so one bad write doesn't lose the whole history
+pub fn load_records(path: &Path) -> io::Result<Vec<SessionRecord>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let f = fs::File::open(path)?;
+    let mut records = Vec::new();
+    for line in BufReader::new(f).lines() {
+        if let Ok(record) = serde_json::from_str(&line?) {
+            records.push(record);
+        }
+    }
+    Ok(records)
+}
+
+fn serialize_secs<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_secs())
+}
+
+/// Totals of words and time written
+#[derive(Serialize, Debug, Default, Clone, Copy)]
+pub struct Totals {
+    pub sessions: usize,
+    pub words: usize,
+    #[serde(rename = "duration_secs", serialize_with = "serialize_secs")]
+    pub duration: Duration,
+}
+
+impl Totals {
+    fn add(&mut self, record: &SessionRecord) {
+        self.sessions += 1;
+        self.words += record.words;
+        self.duration += Duration::from_secs(record.duration_secs);
+    }
+}
+
+/// Statistics computed from the session history
+#[derive(Serialize, Debug)]
+pub struct HistorySummary {
+    pub totals: Totals,
+    pub streak: Streak,
+    /// Totals of the most recent days, oldest first
+    pub days: Vec<(NaiveDate, Totals)>,
+    /// Totals of the most recent weeks, by the Monday starting each week, oldest first
+    pub weeks: Vec<(NaiveDate, Totals)>,
+}
+
+/// Monday of the week containing date
+fn week_start(date: NaiveDate) -> NaiveDate {
+    date - Days::new(date.weekday().num_days_from_monday().into())
+}
+
+fn totals_by_day(records: &[SessionRecord]) -> BTreeMap<NaiveDate, Totals> {
+    let mut by_day: BTreeMap<NaiveDate, Totals> = BTreeMap::new();
+    for record in records {
+        by_day
+            .entry(record.date.date_naive())
+            .or_default()
+            .add(record);
+    }
+    by_day
+}
+
+/// Consecutive days of writing.
+/// A day counts if the words written that day met the goal, or if any words were written when
+/// there is no goal
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct Streak {
+    /// Consecutive days up to today, or up to yesterday if today doesn't count yet
+    pub current: usize,
+    /// Most consecutive days
+    pub longest: usize,
+    /// Whether today counts towards the streak yet
+    pub written_today: bool,
+    /// Words written today
+    pub today_words: usize,
+    /// Words needed each day
+    pub goal: Option<usize>,
+}
+
+impl Streak {
+    pub fn new(records: &[SessionRecord], today: NaiveDate, goal: Option<usize>) -> Self {
+        Self::from_days(&totals_by_day(records), today, goal)
+    }
+
+    fn from_days(
+        by_day: &BTreeMap<NaiveDate, Totals>,
+        today: NaiveDate,
+        goal: Option<usize>,
+    ) -> Self {
+        let min_words = goal.unwrap_or(1);
+        let mut longest = 0;
+        let mut run = 0;
+        let mut previous: Option<NaiveDate> = None;
+        for (&day, totals) in by_day {
+            if totals.words < min_words {
+                continue;
+            }
+            run = match previous {
+                Some(previous) if previous.succ_opt() == Some(day) => run + 1,
+                _ => 1,
+            };
+            longest = longest.max(run);
+            previous = Some(day);
+        }
+        // The current streak is only alive if the last day was today or yesterday
+        let current = match previous {
+            Some(last) if last == today || last.succ_opt() == Some(today) => run,
+            _ => 0,
+        };
+        let today_words = by_day.get(&today).map_or(0, |totals| totals.words);
+        Self {
+            current,
+            longest,
+            written_today: today_words >= min_words,
+            today_words,
+            goal,
+        }
+    }
+
+    /// Current streak, including a session in progress that has written `session_words` words
+    pub fn with_session(&self, session_words: usize) -> usize {
+        if !self.written_today && self.today_words + session_words >= self.goal.unwrap_or(1) {
+            self.current + 1
+        } else {
+            self.current
+        }
+    }
+}
+
+impl fmt::Display for Streak {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Current streak: {} days", self.current)?;
+        writeln!(f, "Longest streak: {} days", self.longest)?;
+        let today = if self.written_today {
+            "done"
+        } else {
+            "not yet"
+        };
+        match self.goal {
+            Some(goal) => writeln!(
+                f,
+                "Today:          {} ({}/{} words)",
+                today, self.today_words, goal
+            ),
+            None => writeln!(f, "Today:          {} ({} words)", today, self.today_words),
+        }
+    }
+}
+
+impl HistorySummary {
+    /// Summarize records, with totals for the last `days` days and last `weeks` weeks.
+    /// Streaks count days meeting `goal` words
+    pub fn new(
+        records: &[SessionRecord],
+        today: NaiveDate,
+        goal: Option<usize>,
+        days: usize,
+        weeks: usize,
+    ) -> Self {
+        let mut totals = Totals::default();
+        let mut by_week: BTreeMap<NaiveDate, Totals> = BTreeMap::new();
+        for record in records {
+            totals.add(record);
+            by_week
+                .entry(week_start(record.date.date_naive()))
+                .or_default()
+                .add(record);
+        }
+        let by_day = totals_by_day(records);
+
+        let recent =
+            |map: &BTreeMap<NaiveDate, Totals>, count: usize, step: u64, last: NaiveDate| {
+                (0..count as u64)
+                    .rev()
+                    .filter_map(|i| last.checked_sub_days(Days::new(i * step)))
+                    .map(|date| (date, map.get(&date).copied().unwrap_or_default()))
+                    .collect()
+            };
+
+        Self {
+            totals,
+            streak: Streak::from_days(&by_day, today, goal),
+            days: recent(&by_day, days, 1, today),
+            weeks: recent(&by_week, weeks, 7, week_start(today)),
+        }
+    }
+}
+
+impl fmt::Display for HistorySummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let totals = &self.totals;
+        writeln!(f, "Sessions:       {}", totals.sessions)?;
+        writeln!(f, "Total words:    {}", totals.words)?;
+        writeln!(f, "Total time:     {}", format_duration(totals.duration))?;
+        if totals.sessions > 0 {
+            let sessions = totals.sessions as u32;
+            writeln!(f, "Average words:  {}", totals.words / totals.sessions)?;
+            writeln!(
+                f,
+                "Average time:   {}",
+                format_duration(totals.duration / sessions)
+            )?;
+        }
+        write!(f, "{}", self.streak)?;
+
+        if !self.days.is_empty() {
+            writeln!(f, "\nLast {} days:", self.days.len())?;
+            for (date, totals) in &self.days {
+                writeln!(
+                    f,
+                    "  {}  {:>6} words  {:>8}",
+                    date,
+                    totals.words,
+                    format_duration(totals.duration)
+                )?;
+            }
+        }
+        if !self.weeks.is_empty() {
+            writeln!(f, "\nLast {} weeks:", self.weeks.len())?;
+            for (date, totals) in &self.weeks {
+                writeln!(
+                    f,
+                    "  Week of {}  {:>6} words  {:>8}",
+                    date,
+                    totals.words,
+                    format_duration(totals.duration)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, day).unwrap()
+    }
+
+    /// Session ending at noon of day in March 2024
+    fn record(day: u32, words: usize, duration_secs: u64) -> SessionRecord {
+        let noon = date(day).and_hms_opt(12, 0, 0).unwrap();
+        SessionRecord {
+            date: Local.from_local_datetime(&noon).unwrap(),
+            file: PathBuf::from("pages.txt"),
+            duration_secs,
+            words,
+            mode: String::from("normal"),
+            goal_met: None,
+            prompt: None,
+        }
+    }
+
+    #[test]
+    fn summarizes_days_and_weeks() {
+        // Wednesday 6 March 2024, in the week starting Monday 4 March
+        let records = [
+            record(1, 300, 600),
+            record(5, 200, 300),
+            record(6, 100, 60),
+            record(6, 150, 120),
+        ];
+        let summary = HistorySummary::new(&records, date(6), None, 3, 2);
+
+        assert_eq!(summary.totals.sessions, 4);
+        assert_eq!(summary.totals.words, 750);
+        assert_eq!(summary.totals.duration, Duration::from_secs(1080));
+
+        let days: Vec<_> = summary
+            .days
+            .iter()
+            .map(|(date, totals)| (*date, totals.words, totals.sessions))
+            .collect();
+        assert_eq!(
+            days,
+            [(date(4), 0, 0), (date(5), 200, 1), (date(6), 250, 2)]
+        );
+        let weeks: Vec<_> = summary
+            .weeks
+            .iter()
+            .map(|(date, totals)| (*date, totals.words))
+            .collect();
+        assert_eq!(
+            weeks,
+            [
+                (NaiveDate::from_ymd_opt(2024, 2, 26).unwrap(), 300),
+                (date(4), 450)
+            ]
+        );
+    }
+
+    #[test]
+    fn loads_records_skipping_bad_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history").join("sessions.jsonl");
+        assert!(load_records(&path).unwrap().is_empty());
+
+        append_record(&path, &record(5, 200, 300)).unwrap();
+        let mut f = OpenOptions::new().append(true).open(&path).unwrap();
+        f.write_all(b"{\"date\": \"not a date\"}\n").unwrap();
+        append_record(&path, &record(6, 100, 60)).unwrap();
+
+        let words: Vec<_> = load_records(&path)
+            .unwrap()
+            .iter()
+            .map(|record| record.words)
+            .collect();
+        assert_eq!(words, [200, 100]);
+    }
+
+    #[test]
+    fn streaks_count_consecutive_days() {
+        let records = [
+            record(1, 100, 60),
+            record(2, 100, 60),
+            record(3, 100, 60),
+            // Gap on the 4th
+            record(5, 100, 60),
+            record(6, 100, 60),
+        ];
+        // Today doesn't break the streak until it is over
+        let streak = Streak::new(&records, date(7), None);
+        assert_eq!((streak.current, streak.longest), (2, 3));
+        assert!(!streak.written_today);
+        assert_eq!(streak.with_session(0), 2);
+        assert_eq!(streak.with_session(1), 3);
+
+        let streak = Streak::new(&records, date(6), None);
+        assert_eq!((streak.current, streak.today_words), (2, 100));
+        assert!(streak.written_today);
+        // Today already counts
+        assert_eq!(streak.with_session(50), 2);
+
+        let streak = Streak::new(&records, date(8), None);
+        assert_eq!((streak.current, streak.longest), (0, 3));
+    }
+
+    #[test]
+    fn streaks_only_count_days_meeting_goal() {
+        let records = [
+            record(4, 500, 600),
+            record(5, 300, 300),
+            record(5, 200, 300),
+            record(6, 100, 60),
+        ];
+        let streak = Streak::new(&records, date(6), Some(500));
+        assert_eq!((streak.current, streak.longest), (2, 2));
+        assert!(!streak.written_today);
+        assert_eq!(streak.today_words, 100);
+        assert_eq!(streak.with_session(399), 2);
+        assert_eq!(streak.with_session(400), 3);
+
+        let streak = Streak::new(&records, date(6), Some(600));
+        assert_eq!((streak.current, streak.longest), (0, 0));
+    }
+}
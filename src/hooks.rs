@@ -0,0 +1,317 @@
+use std::fmt;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::buffer::Buffer;
+use crate::event::{AppEvent, EventSender};
+
+/// How long hooks may run before they are killed, unless set in the config file
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest timeout that can be set, so a mistyped value doesn't leave a hook running forever
+pub const MAX_TIMEOUT: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How often a running hook is checked for having exited
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Point of a session that a hook runs at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    Start,
+    Save,
+    Goal,
+    Exit,
+    SaveError,
+}
+
+impl fmt::Display for HookEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            HookEvent::Start => "on_start",
+            HookEvent::Save => "on_save",
+            HookEvent::Goal => "on_goal",
+            HookEvent::Exit => "on_exit",
+            HookEvent::SaveError => "on_save_error",
+        })
+    }
+}
+
+/// Shell commands run at points of a session, as written in the user config file.
+///
+/// Commands run with `sh -c`, with the session described in environment variables:
+/// `HEMM_EVENT`, `HEMM_FILE`, `HEMM_WORDS`, `HEMM_SESSION_WORDS`, `HEMM_DURATION` (seconds),
+/// and `HEMM_ERROR` for `on_save_error`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Hooks {
+    /// When the editor opens
+    pub on_start: Option<String>,
+    /// After the file is saved
+    pub on_save: Option<String>,
+    /// When the session first reaches `--goal` words
+    pub on_goal: Option<String>,
+    /// After the final save, when the editor closes
+    pub on_exit: Option<String>,
+    /// When saving the file fails
+    pub on_save_error: Option<String>,
+    /// Seconds a hook may run before it is killed
+    /// default: 10
+    pub timeout: Option<u64>,
+}
+
+/// State of the session passed to hooks
+#[derive(Debug, Clone)]
+pub struct HookContext {
+    pub file: PathBuf,
+    pub words: usize,
+    pub session_words: usize,
+    pub duration: Duration,
+    /// Why saving failed, for `on_save_error`
+    pub error: Option<String>,
+}
+
+impl HookContext {
+    pub fn new(buffer: &Buffer) -> Self {
+        Self {
+            file: buffer.path().to_path_buf(),
+            words: buffer.word_count(),
+            session_words: buffer.session_word_count(),
+            duration: buffer.stats().elapsed(),
+            error: None,
+        }
+    }
+
+    pub fn with_error(mut self, error: impl fmt::Display) -> Self {
+        self.error = Some(error.to_string());
+        self
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum HookError {
+    #[error("{0} hook could not be started: {1}")]
+    Spawn(HookEvent, io::Error),
+
+    #[error("{0} hook timed out after {1:?}")]
+    TimedOut(HookEvent, Duration),
+
+    #[error("{0} hook failed ({1}): {2}")]
+    Failed(HookEvent, ExitStatus, String),
+}
+
+impl Hooks {
+    /// Command run at event, if any
+    pub fn command(&self, event: HookEvent) -> Option<&str> {
+        match event {
+            HookEvent::Start => &self.on_start,
+            HookEvent::Save => &self.on_save,
+            HookEvent::Goal => &self.on_goal,
+            HookEvent::Exit => &self.on_exit,
+            HookEvent::SaveError => &self.on_save_error,
+        }
+        .as_deref()
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_TIMEOUT)
+    }
+
+    /// Run hook of event, waiting for it to exit or time out. Does nothing if there is no hook
+    pub fn run(&self, event: HookEvent, context: &HookContext) -> Result<(), HookError> {
+        let Some(command) = self.command(event) else {
+            return Ok(());
+        };
+        let mut child = shell(command)
+            .env("HEMM_EVENT", event.to_string())
+            .env("HEMM_FILE", &context.file)
+            .env("HEMM_WORDS", context.words.to_string())
+            .env("HEMM_SESSION_WORDS", context.session_words.to_string())
+            .env("HEMM_DURATION", context.duration.as_secs().to_string())
+            .env("HEMM_ERROR", context.error.as_deref().unwrap_or_default())
+            // Hooks must not draw over the editor
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|err| HookError::Spawn(event, err))?;
+
+        // Output is read on threads of its own, so a hook can't block on a full pipe
+        let (sender, receiver) = mpsc::channel();
+        let pipes: [Option<Box<dyn Read + Send>>; 2] = [
+            child
+                .stderr
+                .take()
+                .map(|pipe| Box::new(pipe) as Box<dyn Read + Send>),
+            child
+                .stdout
+                .take()
+                .map(|pipe| Box::new(pipe) as Box<dyn Read + Send>),
+        ];
+        for (index, pipe) in pipes.into_iter().enumerate() {
+            let sender = sender.clone();
+            thread::spawn(move || {
+                let mut output = String::new();
+                if let Some(mut pipe) = pipe {
+                    _ = pipe.read_to_string(&mut output);
+                }
+                _ = sender.send((index, output));
+            });
+        }
+
+        let timeout = self.timeout();
+        // No deadline if the timeout is too long to represent
+        let deadline = Instant::now().checked_add(timeout);
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break status,
+                Ok(None) if !deadline.is_some_and(|deadline| Instant::now() >= deadline) => {
+                    thread::sleep(POLL_INTERVAL)
+                }
+                _ => {
+                    _ = child.kill();
+                    _ = child.wait();
+                    return Err(HookError::TimedOut(event, timeout));
+                }
+            }
+        };
+        if status.success() {
+            return Ok(());
+        }
+
+        // Processes started in the background by the hook may keep its output open
+        let mut outputs = [String::new(), String::new()];
+        for _ in 0..outputs.len() {
+            let remaining = deadline.map_or(Duration::MAX, |deadline| {
+                deadline.saturating_duration_since(Instant::now())
+            });
+            match receiver.recv_timeout(remaining) {
+                Ok((index, output)) => outputs[index] = output,
+                Err(_) => break,
+            }
+        }
+        // The last line of output usually says what went wrong, errors take precedence
+        let message = outputs
+            .iter()
+            .find_map(|output| output.lines().rev().find(|line| !line.trim().is_empty()))
+            .unwrap_or_default()
+            .trim()
+            .to_string();
+        Err(HookError::Failed(event, status, message))
+    }
+
+    /// Run hook of event on a thread of its own, showing failures in the status line
+    pub fn spawn(&self, event: HookEvent, context: HookContext, events: &EventSender) {
+        if self.command(event).is_none() {
+            return;
+        }
+        let hooks = self.clone();
+        let events = events.clone();
+        thread::spawn(move || {
+            if let Err(err) = hooks.run(event, &context) {
+                _ = events.send(AppEvent::Message(err.to_string()));
+            }
+        });
+    }
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn context() -> HookContext {
+        HookContext {
+            file: PathBuf::from("/tmp/pages.txt"),
+            words: 750,
+            session_words: 250,
+            duration: Duration::from_secs(600),
+            error: None,
+        }
+    }
+
+    #[test]
+    fn hooks_receive_session_in_environment() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("out");
+        let hooks = Hooks {
+            on_goal: Some(format!(
+                "echo $HEMM_EVENT $HEMM_FILE $HEMM_WORDS $HEMM_SESSION_WORDS $HEMM_DURATION > {}",
+                out.display()
+            )),
+            ..Default::default()
+        };
+        hooks.run(HookEvent::Goal, &context()).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&out).unwrap(),
+            "on_goal /tmp/pages.txt 750 250 600\n"
+        );
+        // Events without a hook do nothing
+        hooks.run(HookEvent::Exit, &context()).unwrap();
+    }
+
+    #[test]
+    fn failures_report_output_and_timeouts() {
+        let hooks = Hooks {
+            on_save: Some(String::from(
+                "echo syncing; echo 'remote unreachable' >&2; exit 3",
+            )),
+            on_exit: Some(String::from("sleep 5")),
+            timeout: Some(1),
+            ..Default::default()
+        };
+        let err = hooks.run(HookEvent::Save, &context()).unwrap_err();
+        assert!(
+            matches!(&err, HookError::Failed(HookEvent::Save, status, message)
+            if status.code() == Some(3) && message == "remote unreachable")
+        );
+
+        let started = Instant::now();
+        let err = hooks.run(HookEvent::Exit, &context()).unwrap_err();
+        assert!(matches!(err, HookError::TimedOut(HookEvent::Exit, _)));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn rejects_timeouts_too_long() {
+        use crate::cli::Cli;
+        use crate::config::{Config, ConfigErrorType};
+        use clap::Parser;
+
+        let mut cli = Cli::parse_from(["hemm", "/tmp/pages.txt"]);
+        cli.hooks = Some(Hooks {
+            on_save: Some(String::from("true")),
+            timeout: Some(u64::MAX),
+            ..Hooks::default()
+        });
+        let err = Config::new(&cli).unwrap_err();
+        assert!(matches!(
+            err.error_type,
+            ConfigErrorType::HookTimeoutTooLong
+        ));
+        // Still never panics if run anyway
+        let hooks = cli.hooks.unwrap();
+        hooks.run(HookEvent::Save, &context()).unwrap();
+    }
+}
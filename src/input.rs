@@ -1,18 +1,24 @@
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, Condvar, Mutex};
+use std::io::{stderr, Write};
+use std::sync::{Arc, Mutex};
 use std::thread::{self, JoinHandle};
+use std::time::Duration;
 
-use crossterm::event::{Event, KeyCode};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
 use tui_textarea::CursorMove;
 
 use crate::buffer::Buffer;
-use crate::config::{Config, WritingMode};
+use crate::config::Config;
+use crate::event::{AppEvent, EventSender, Shutdown};
+use crate::recording::Recorder;
 
 /// The user input is handled on its own thread in order to prevent the possibility
 /// of an input event being missed between loops.
 
-/// Starts thread that captures terminal input events
-/// Currently no sleep between loops
+/// How long to wait for input before checking if the thread should stop
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Starts thread that captures terminal input events, applies them to the buffer,
+/// and notifies the render loop
 ///
 /// This thread is responsible for giving the user a way to end
 /// the program, otherwise, it won't end otherwise. The terminal being in raw mode prevents the
@@ -20,72 +26,52 @@ use crate::config::{Config, WritingMode};
 /// Maybe there is a way to propogate the event to the terminal to let it handle the signaling?
 pub fn start_input_thread(
     buffer_handle: Arc<Mutex<Buffer>>,
-    running_handle: Arc<AtomicBool>,
-    condvar: Arc<Condvar>,
+    events: EventSender,
+    shutdown: Shutdown,
+    mut recorder: Option<Recorder>,
     config: &Config,
 ) -> JoinHandle<()> {
-    let hemingway_mode = config.writing_mode == WritingMode::Hemingway;
+    let hemingway_mode = config.writing_mode.is_restricted();
     thread::spawn(move || {
-        while running_handle.load(Ordering::SeqCst) {
+        while !shutdown.requested() {
+            if !crossterm::event::poll(POLL_INTERVAL).unwrap_or(false) {
+                continue;
+            }
             if let Ok(evt) = crossterm::event::read() {
                 match evt {
                     Event::Key(key_event) => {
-                        let mut buffer = buffer_handle.lock().unwrap();
-                        match key_event.code {
-                            KeyCode::Char(c) => {
-                                buffer.textarea.insert_char(c);
-                                buffer.mark_modified();
-                            }
-                            KeyCode::Left
-                            | KeyCode::Right
-                            | KeyCode::Up
-                            | KeyCode::Down
-                            | KeyCode::Home
-                            | KeyCode::End
-                                if !hemingway_mode =>
-                            {
-                                // PageUp PageDown not implemented
-                                buffer.textarea.move_cursor(match key_event.code {
-                                    KeyCode::Left => CursorMove::Back,
-                                    KeyCode::Right => CursorMove::Forward,
-                                    KeyCode::Up => CursorMove::Up,
-                                    KeyCode::Down => CursorMove::Down,
-                                    KeyCode::Home => CursorMove::Head,
-                                    KeyCode::End => CursorMove::End,
-                                    _ => unreachable!(),
-                                });
-                            }
-                            KeyCode::PageUp if !hemingway_mode => {
-                                buffer.textarea.scroll((-10, 0));
-                            }
-                            KeyCode::PageDown if !hemingway_mode => {
-                                buffer.textarea.scroll((10, 0));
+                        if let Some(offset) = chapter_offset(key_event) {
+                            if !hemingway_mode {
+                                _ = events.send(AppEvent::SwitchChapter(offset));
                             }
-                            KeyCode::Backspace if !hemingway_mode => {
-                                buffer.textarea.delete_char();
-                                buffer.mark_modified();
-                            }
-                            KeyCode::Delete if !hemingway_mode => {
-                                buffer.textarea.delete_next_char();
-                                buffer.mark_modified();
-                            }
-                            KeyCode::Enter => {
-                                buffer.textarea.insert_newline();
-                                buffer.mark_modified();
-                            }
-                            KeyCode::Tab => {
-                                buffer.textarea.insert_tab();
-                                buffer.mark_modified();
-                            }
-                            KeyCode::Esc => {
-                                // Exit the program
-                                running_handle.store(false, Ordering::SeqCst);
-                                // Wake up all sleeping threads
-                                condvar.notify_all();
-                                // TODO: Display message for user
+                            continue;
+                        }
+                        // Written before taking the lock, so a slow disk doesn't hold up other threads
+                        if let Some(recorder) = recorder.as_mut() {
+                            if let Err(err) = recorder.record(key_event.code) {
+                                buffer_handle
+                                    .lock()
+                                    .unwrap()
+                                    .set_message(Some(String::from("Error when recording")));
+                                stderr()
+                                    .write_all(
+                                        format!("Encountered error when recording: {}", err)
+                                            .as_bytes(),
+                                    )
+                                    .unwrap_or(());
                             }
-                            _ => {}
                         }
+                        let mut buffer = buffer_handle.lock().unwrap();
+                        if handle_key(&mut buffer, key_event, hemingway_mode) {
+                            // Exit the program
+                            _ = events.send(AppEvent::Quit);
+                            // TODO: Display message for user
+                            return;
+                        }
+                        _ = events.send(AppEvent::Input);
+                    }
+                    Event::Resize(_, _) => {
+                        _ = events.send(AppEvent::Resize);
                     }
                     _ => {}
                 }
@@ -93,3 +79,78 @@ pub fn start_input_thread(
         }
     })
 }
+
+/// Chapters to move by for key press: Ctrl+PageDown opens the next chapter of a project,
+/// and Ctrl+PageUp the previous one
+pub fn chapter_offset(key_event: KeyEvent) -> Option<isize> {
+    if !key_event.modifiers.contains(KeyModifiers::CONTROL) {
+        return None;
+    }
+    match key_event.code {
+        KeyCode::PageDown => Some(1),
+        KeyCode::PageUp => Some(-1),
+        _ => None,
+    }
+}
+
+/// Type text into buffer one key at a time, as if typed by the user. Line breaks are typed as Enter
+pub fn type_text(buffer: &mut Buffer, text: &str, hemingway_mode: bool) {
+    for c in text.chars() {
+        let code = match c {
+            '\n' => KeyCode::Enter,
+            '\t' => KeyCode::Tab,
+            c => KeyCode::Char(c),
+        };
+        handle_key(
+            buffer,
+            KeyEvent::new(code, KeyModifiers::NONE),
+            hemingway_mode,
+        );
+    }
+}
+
+/// Apply key press to buffer. Navigation and deletion are ignored in restricted writing modes.
+/// Returns true if the user asked to exit
+pub fn handle_key(buffer: &mut Buffer, key_event: KeyEvent, hemingway_mode: bool) -> bool {
+    match key_event.code {
+        KeyCode::Char(c) => buffer.edit(|textarea| textarea.insert_char(c)),
+        KeyCode::Left
+        | KeyCode::Right
+        | KeyCode::Up
+        | KeyCode::Down
+        | KeyCode::Home
+        | KeyCode::End
+            if !hemingway_mode =>
+        {
+            // PageUp PageDown not implemented
+            buffer.textarea.move_cursor(match key_event.code {
+                KeyCode::Left => CursorMove::Back,
+                KeyCode::Right => CursorMove::Forward,
+                KeyCode::Up => CursorMove::Up,
+                KeyCode::Down => CursorMove::Down,
+                KeyCode::Home => CursorMove::Head,
+                KeyCode::End => CursorMove::End,
+                _ => unreachable!(),
+            });
+        }
+        KeyCode::PageUp if !hemingway_mode => {
+            buffer.textarea.scroll((-10, 0));
+        }
+        KeyCode::PageDown if !hemingway_mode => {
+            buffer.textarea.scroll((10, 0));
+        }
+        KeyCode::Backspace if !hemingway_mode => buffer.edit(|textarea| {
+            textarea.delete_char();
+        }),
+        KeyCode::Delete if !hemingway_mode => buffer.edit(|textarea| {
+            textarea.delete_next_char();
+        }),
+        KeyCode::Enter => buffer.edit(|textarea| textarea.insert_newline()),
+        KeyCode::Tab => buffer.edit(|textarea| {
+            textarea.insert_tab();
+        }),
+        KeyCode::Esc => return true,
+        _ => {}
+    }
+    false
+}
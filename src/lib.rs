@@ -5,8 +5,31 @@
 //!
 
 pub mod autosave;
+pub mod broadcast;
 pub mod buffer;
 pub mod cli;
 pub mod config;
+#[cfg(unix)]
+pub mod control;
+pub mod crypto;
+pub mod event;
+pub mod export;
+pub mod file_format;
+pub mod git;
+pub mod history;
+pub mod hooks;
 pub mod input;
+pub mod project;
+pub mod prompt;
+pub mod recording;
+pub mod session;
+pub mod snapshot;
+pub mod stats;
+pub mod status;
+pub mod template;
+pub mod theme;
 pub mod timer;
+pub mod ui;
+pub mod wrap;
+pub mod writer;
+pub mod zip;
@@ -6,61 +6,363 @@
 //! `hemm <output_filename>`
 //! `hemm -h`
 use std::error::Error;
+use std::fs;
 use std::io;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, Condvar, Mutex};
-use std::thread::JoinHandle;
-use std::time::Duration;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
+use chrono::Local;
 use clap::Parser;
 use crossterm::cursor::SetCursorStyle;
-use crossterm::event::{DisableMouseCapture, EnableMouseCapture};
+use crossterm::event::{
+    self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyModifiers,
+};
 use crossterm::terminal::{
     disable_raw_mode, enable_raw_mode, is_raw_mode_enabled, EnterAlternateScreen,
     LeaveAlternateScreen,
 };
 use hemm::autosave::start_autosave_thread;
+use hemm::broadcast::{watch, BroadcastAddr, BroadcastUpdate, Broadcaster};
 use hemm::buffer::Buffer;
-use hemm::cli::Cli;
-use hemm::config::{load_user_config, Config};
+use hemm::cli::{
+    Cli, Command, ExportArgs, ProjectCommand, ReplayArgs, SnapshotsCommand, WatchArgs,
+};
+use hemm::config::{
+    get_history_path, get_project_state_path, get_snapshots, load_user_config, merge_user_config,
+    Config,
+};
+#[cfg(unix)]
+use hemm::control::start_control_thread;
+use hemm::crypto::is_encrypted;
+use hemm::event::{event_bus, shutdown_channel, AppEvent};
+use hemm::export::{export, Metadata};
+use hemm::file_format::FileFormat;
+use hemm::git::commit_file;
+use hemm::history::{append_record, load_records, HistorySummary, SessionRecord, Streak};
+use hemm::hooks::{HookContext, HookEvent};
 use hemm::input::start_input_thread;
+use hemm::project::Project;
+use hemm::prompt::PromptLibrary;
+use hemm::recording::{Recorder, Recording};
+use hemm::session::Session;
 use hemm::timer::start_timer_thread;
+use hemm::ui::draw;
+use rpassword::prompt_password;
 use tui::backend::CrosstermBackend;
-use tui::layout::{Constraint, Direction, Layout};
-use tui::text::Span;
-use tui::widgets::Paragraph;
 use tui::Terminal;
 
+/// How often the watch view checks for key presses while waiting for updates
+const WATCH_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
 fn main() -> Result<(), Box<dyn Error>> {
     let cli = Cli::parse();
-    dbg!(&cli);
+    if let Some(command) = cli.command.clone() {
+        let cli = merge_user_config(cli)?;
+        return run_command(&command, cli);
+    }
 
     let config = load_user_config(cli)?;
-    dbg!(&config);
+    run(&config, None)?;
+
+    Ok(())
+}
+
+/// Run a subcommand instead of the editor
+fn run_command(command: &Command, cli: Cli) -> Result<(), Box<dyn Error>> {
+    match command {
+        Command::Stats(args) => {
+            let Some(history_path) = get_history_path(&cli) else {
+                println!("Session history is disabled");
+                return Ok(());
+            };
+            let records = load_records(&history_path)?;
+            let today = Local::now().date_naive();
+            if args.streak {
+                let streak = Streak::new(&records, today, cli.goal);
+                if args.json {
+                    println!("{}", serde_json::to_string(&streak)?);
+                } else {
+                    print!("{}", streak);
+                }
+            } else {
+                let summary = HistorySummary::new(&records, today, cli.goal, args.days, args.weeks);
+                if args.json {
+                    println!("{}", serde_json::to_string(&summary)?);
+                } else {
+                    print!("{}", summary);
+                }
+            }
+        }
+        Command::Snapshots(SnapshotsCommand::List { file }) => {
+            for snapshot in get_snapshots(&cli, file)?.list()? {
+                if let Some(name) = snapshot.file_name() {
+                    println!("{}", name.to_string_lossy());
+                }
+            }
+        }
+        Command::Snapshots(SnapshotsCommand::Restore { file, snapshot }) => {
+            let snapshots = get_snapshots(&cli, file)?;
+            let Some(snapshot_path) = snapshots.find(snapshot.as_deref())? else {
+                return Err(format!("No snapshot of {:?} found", file).into());
+            };
+            snapshots.restore(file, &snapshot_path, &Local::now())?;
+            println!("Restored {:?} from {:?}", file, snapshot_path);
+        }
+        Command::Replay(args) => replay(cli, args)?,
+        Command::Watch(args) => watch_broadcast(cli, args)?,
+        Command::Project(ProjectCommand::Open { manifest }) => {
+            let project = Project::load(manifest)?.with_state(get_project_state_path(manifest)?)?;
+            let mut cli = cli;
+            cli.path = Some(project.chapter_path(project.current()));
+            let config = Config::new(&cli)?;
+            if config.encrypt {
+                return Err("Encrypted projects are not supported".into());
+            }
+            run(&config, Some(project))?;
+        }
+        Command::Project(ProjectCommand::Stats { manifest, json }) => {
+            let summary = Project::load(manifest)?.summary()?;
+            if *json {
+                println!("{}", serde_json::to_string(&summary)?);
+            } else {
+                print!("{}", summary);
+            }
+        }
+        Command::Project(ProjectCommand::Build { manifest, output }) => {
+            let project = Project::load(manifest)?;
+            let output = output.clone().unwrap_or_else(|| project.output_path());
+            fs::write(&output, project.build()?)?;
+            println!("Built {:?}", output);
+        }
+        Command::Export(args) => export_file(&cli, args)?,
+    }
+    Ok(())
+}
+
+/// Convert a file to the format in args, with title and author from the config
+fn export_file(cli: &Cli, args: &ExportArgs) -> Result<(), Box<dyn Error>> {
+    let data = fs::read(&args.file)?;
+    if is_encrypted(&data) {
+        return Err("Encrypted files can't be exported".into());
+    }
+    let (text, _) = FileFormat::decode(&data);
+    let metadata = Metadata {
+        title: cli.title.clone(),
+        author: cli.author.clone(),
+    };
+    let output = args
+        .output
+        .clone()
+        .unwrap_or_else(|| args.file.with_extension(args.format.extension()));
+    if output == args.file {
+        return Err("Export would overwrite the file, choose another with --output".into());
+    }
+    fs::write(&output, export(&text, args.format, &metadata))?;
+    println!("Exported to {:?}", output);
+    Ok(())
+}
+
+/// Play back a recording in a read-only view, until the user presses Esc
+fn replay(mut cli: Cli, args: &ReplayArgs) -> Result<(), Box<dyn Error>> {
+    if !(args.speed.is_finite() && args.speed > 0.0) {
+        return Err("Speed must be greater than 0".into());
+    }
+    let recording = Recording::load(&args.file)?;
+    // Show the recorded text in place of the file it was written to. Nothing is saved
+    cli.path = recording.header.file.clone();
+    cli.hemingway = Some(recording.header.mode == "hemingway");
+    cli.blind = Some(recording.header.mode == "blind");
+    let config = Config::new(&cli)?;
+    let mut session =
+        Session::with_buffer(&config, Buffer::from_text(&config, &recording.header.text));
+
+    let mut stdout = io::stdout();
+    enable_raw_mode()?;
+    crossterm::execute!(stdout, EnterAlternateScreen)?;
+    let mut term = Terminal::new(CrosstermBackend::new(stdout))?;
+
+    let started = Instant::now();
+    let mut keys = recording.keys.iter().peekable();
+    loop {
+        term.draw(|f| draw(f, &session))?;
+        let timeout = match keys.peek() {
+            Some(key) => key
+                .time()
+                .div_f64(args.speed)
+                .saturating_sub(started.elapsed()),
+            None => Duration::from_secs(60),
+        };
+        // Keys pressed while watching are ignored, except Esc to exit
+        if event::poll(timeout)? {
+            if let Event::Key(key_event) = event::read()? {
+                if key_event.code == KeyCode::Esc {
+                    break;
+                }
+            }
+            continue;
+        }
+        let Some(key) = keys.next() else {
+            continue;
+        };
+        // Esc ended the recorded session, the replay stays open until the viewer exits
+        if let Some(code) = key.code().filter(|code| *code != KeyCode::Esc) {
+            session.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+        }
+        session.handle_event(AppEvent::Tick(key.time()));
+        if keys.peek().is_none() {
+            let message = String::from("Replay finished, press Esc to exit");
+            session.buffer().lock().unwrap().set_message(Some(message));
+        }
+    }
+
+    term.show_cursor()?;
+    disable_raw_mode()?;
+    crossterm::execute!(term.backend_mut(), LeaveAlternateScreen)?;
+    Ok(())
+}
+
+/// Follow a broadcast session in a read-only view, until the user presses Esc
+fn watch_broadcast(mut cli: Cli, args: &WatchArgs) -> Result<(), Box<dyn Error>> {
+    let addr = BroadcastAddr::parse(&args.addr)?;
+    let updates = watch(&addr)?;
+    // Show the broadcast text instead of a file. Nothing is saved
+    cli.path = Some(args.addr.clone().into());
+    let config = Config::new(&cli)?;
+    let mut session = Session::with_buffer(&config, Buffer::from_text(&config, ""));
 
-    run(&config)?;
+    // Updates are read on a thread of their own, so the view can keep polling for Esc
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for update in updates {
+            if sender.send(update).is_err() {
+                break;
+            }
+        }
+    });
 
+    let mut stdout = io::stdout();
+    enable_raw_mode()?;
+    crossterm::execute!(stdout, EnterAlternateScreen)?;
+    let mut term = Terminal::new(CrosstermBackend::new(stdout))?;
+
+    let mut ended = false;
+    loop {
+        term.draw(|f| draw(f, &session))?;
+        // Once the broadcast has ended, there is nothing to wait for but Esc
+        let timeout = if ended {
+            Duration::from_secs(60)
+        } else {
+            Duration::ZERO
+        };
+        // Keys pressed while watching are ignored, except Esc to exit
+        if event::poll(timeout)? {
+            if let Event::Key(key_event) = event::read()? {
+                if key_event.code == KeyCode::Esc {
+                    break;
+                }
+            }
+            continue;
+        }
+        if ended {
+            continue;
+        }
+        let message = match receiver.recv_timeout(WATCH_POLL_INTERVAL) {
+            Ok(Ok(update)) => {
+                *session.buffer().lock().unwrap() = Buffer::from_text(&config, &update.text);
+                session.handle_event(AppEvent::Tick(Duration::from_millis(update.elapsed_ms)));
+                continue;
+            }
+            Err(RecvTimeoutError::Timeout) => continue,
+            Ok(Err(err)) => format!("Broadcast failed: {}, press Esc to exit", err),
+            Err(RecvTimeoutError::Disconnected) => {
+                String::from("Broadcast ended, press Esc to exit")
+            }
+        };
+        ended = true;
+        session.buffer().lock().unwrap().set_message(Some(message));
+    }
+
+    term.show_cursor()?;
+    disable_raw_mode()?;
+    crossterm::execute!(term.backend_mut(), LeaveAlternateScreen)?;
     Ok(())
 }
 
-fn run(config: &Config) -> Result<(), Box<dyn Error>> {
+/// Prompt for passphrase of encrypted file, confirming it if the file is new
+fn read_passphrase(config: &Config) -> Result<String, Box<dyn Error>> {
+    let passphrase = prompt_password("Passphrase: ")?;
+    if !config.get_output_path().exists() {
+        let confirmation = prompt_password("Confirm passphrase: ")?;
+        if passphrase != confirmation {
+            return Err("Passphrases do not match".into());
+        }
+    }
+    Ok(passphrase)
+}
+
+fn run(config: &Config, project: Option<Project>) -> Result<(), Box<dyn Error>> {
     // Shared variables
-    let buffer = Arc::new(Mutex::new(Buffer::new(config).unwrap()));
-    // When this becomes false, all threads and program should exit
-    let running = Arc::new(AtomicBool::new(true));
-    // FIXME: Replace condvar with channels because there is no shared data
-    let condvar = Arc::new(Condvar::new());
-    let condmut = Arc::new(Mutex::new(()));
-    let elapsed_time = Arc::new(Mutex::new(Duration::default()));
+    // Passphrase must be read before the terminal enters raw mode
+    let mut session = if config.encrypt {
+        let passphrase = read_passphrase(config)?;
+        Session::with_buffer(config, Buffer::new_encrypted(config, &passphrase)?)
+    } else {
+        Session::new(config)?
+    };
+    if let Some(project) = project {
+        session.set_project(project)?;
+    }
+    let buffer = session.buffer();
+    // Prompt picked for the session, recorded in the session history
+    let prompt = match &config.prompt_file {
+        Some(path) => Some(
+            PromptLibrary::load(path)?
+                .pick(config.prompt_tag.as_deref())?
+                .text
+                .clone(),
+        ),
+        None => None,
+    };
+    if let Some(prompt) = &prompt {
+        session.set_prompt(prompt, config.prompt_placement);
+    }
+    // Streak from session history, shown at startup
+    let streak = config
+        .history_path
+        .as_ref()
+        .and_then(|path| load_records(path).ok())
+        .map(|records| Streak::new(&records, Local::now().date_naive(), config.word_goal));
+    if let Some(streak) = streak {
+        session.set_streak(streak);
+    }
+    // Recording starts from the text loaded into the editor
+    let recorder = match &config.record_path {
+        Some(path) => Some(Recorder::create(
+            path,
+            session.buffer().lock().unwrap().path(),
+            &config.writing_mode.to_string(),
+            &session.text(),
+        )?),
+        None => None,
+    };
+    // Watchers are sent the text after every change and every second
+    let broadcast = match &config.broadcast {
+        Some(addr) => Some(Broadcaster::start(addr)?),
+        None => None,
+    };
+    // Other threads send events to the render loop, which redraws after each one
+    let (events, event_receiver) = event_bus();
+    // Dropping these stops the other threads
+    let mut shutdown_signals = Vec::new();
 
     // Set up SIGINT handler
     {
-        let r = Arc::clone(&running);
-        let cond = Arc::clone(&condvar);
+        let events = events.clone();
         // FIXME: Seems like raw mode prevents SIGINT signal from generating
         ctrlc::set_handler(move || {
-            r.store(false, Ordering::SeqCst);
-            cond.notify_all();
+            _ = events.send(AppEvent::Quit);
         })
         .expect("Error setting Ctrl-C handler");
     }
@@ -77,11 +379,12 @@ fn run(config: &Config) -> Result<(), Box<dyn Error>> {
     // Start background backup thread
     let backup_thread: Option<JoinHandle<()>>;
     if config.use_autosave {
+        let (signal, shutdown) = shutdown_channel();
+        shutdown_signals.push(signal);
         backup_thread = Some(start_autosave_thread(
             Arc::clone(&buffer),
-            Arc::clone(&running),
-            Arc::clone(&condvar),
-            Arc::clone(&condmut),
+            events.clone(),
+            shutdown,
             &config,
         ));
     } else {
@@ -89,97 +392,208 @@ fn run(config: &Config) -> Result<(), Box<dyn Error>> {
     }
 
     // Start timer
+    // Status line fields and watchers need elapsed time
     let timer_thread: Option<JoinHandle<()>>;
-    if config.show_timer {
-        timer_thread = Some(start_timer_thread(
-            Arc::clone(&elapsed_time),
-            Arc::clone(&running),
-            Arc::clone(&condvar),
-            Arc::clone(&condmut),
-            &config,
-        ));
+    if config.show_status_line || broadcast.is_some() {
+        let (signal, shutdown) = shutdown_channel();
+        shutdown_signals.push(signal);
+        timer_thread = Some(start_timer_thread(events.clone(), shutdown, &config));
     } else {
         timer_thread = None;
     }
 
+    // Start control thread
+    let control_thread: Option<JoinHandle<()>> = match &config.control_socket {
+        #[cfg(unix)]
+        Some(path) => {
+            let (signal, shutdown) = shutdown_channel();
+            shutdown_signals.push(signal);
+            Some(start_control_thread(
+                path,
+                Arc::clone(&buffer),
+                events.clone(),
+                shutdown,
+                &config,
+            )?)
+        }
+        #[cfg(not(unix))]
+        Some(_) => return Err("--control is only supported on Unix".into()),
+        None => None,
+    };
+
     // Start input thread
+    let (signal, shutdown) = shutdown_channel();
+    shutdown_signals.push(signal);
     let input_thread = start_input_thread(
         Arc::clone(&buffer),
-        Arc::clone(&running),
-        Arc::clone(&condvar),
+        events.clone(),
+        shutdown,
+        recorder,
         &config,
     );
 
+    config.hooks.spawn(
+        HookEvent::Start,
+        HookContext::new(&buffer.lock().unwrap()),
+        &events,
+    );
+    // on_goal runs only the first time the goal is reached
+    let mut goal_reached = false;
+
     // FIXME: Cursor style does not change
     crossterm::execute!(term.backend_mut(), SetCursorStyle::SteadyBar).unwrap();
 
-    let layout = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints(
-            [
-                Constraint::Min(1),    // TextArea
-                Constraint::Length(1), // Status line
-            ]
-            .as_ref(),
-        );
-
     // Main render loop
-    while running.load(Ordering::SeqCst) {
-        term.draw(|f| {
-            let chunks = layout.split(f.size());
-            let textarea_layout = Layout::default()
-                .direction(Direction::Vertical)
-                .constraints(
-                    [
-                        Constraint::Length(2), // Margin
-                        Constraint::Min(1),    // Main area
-                        Constraint::Length(1), // One less row margin on bottom due to status line
-                    ]
-                    .as_ref(),
-                )
-                .horizontal_margin(4);
-            let textarea_chunk = textarea_layout.split(chunks[0])[1];
-
-            let mut buffer = buffer.lock().unwrap();
-            let buffer_widget = buffer.textarea.widget();
-            f.render_widget(buffer_widget, textarea_chunk);
-
-            let status_line_layout = Layout::default()
-                .direction(Direction::Horizontal)
-                .constraints([Constraint::Min(1), Constraint::Max(1 + 4 + 1)]);
-            let status_chunks = status_line_layout.split(chunks[1]);
-            let message = match buffer.get_message() {
-                Some(message) => message,
-                None => "",
-            };
-            f.render_widget(Paragraph::new(Span::raw(message)), status_chunks[0]);
-            // TODO: Render timer
-        })
-        .unwrap();
+    while !session.is_finished() {
+        term.draw(|f| draw(f, &session))?;
 
-        // TODO: Check for errors from other threads
         // TODO: File error recovery screen
 
-        // TUI refresh rate
-        let guard = condmut.lock().unwrap();
-        _ = condvar.wait_timeout(guard, Duration::from_millis(50));
+        // Sleep until there is something new to draw
+        match event_receiver.recv() {
+            Ok(event) => {
+                let changed = matches!(event, AppEvent::Input | AppEvent::Tick(_));
+                session.handle_event(event);
+                if let (true, Some((broadcaster, _))) = (changed, &broadcast) {
+                    broadcaster.publish(BroadcastUpdate {
+                        text: session.text(),
+                        elapsed_ms: session.elapsed().as_millis() as u64,
+                    });
+                }
+                if let (true, false, Some(goal)) = (changed, goal_reached, config.word_goal) {
+                    let buffer = buffer.lock().unwrap();
+                    if buffer.session_word_count() >= goal {
+                        goal_reached = true;
+                        let context = HookContext::new(&buffer);
+                        config.hooks.spawn(HookEvent::Goal, context, &events);
+                    }
+                }
+            }
+            // All other threads have stopped if the channel is closed
+            Err(_) => break,
+        }
     }
 
-    // Join threads. They should wake up and stop ASAP from shared condvar.notify_all() call
+    // Join threads. They wake up and stop as soon as their shutdown signal is dropped
+    drop(shutdown_signals);
     if let Some(backup_thread) = backup_thread {
         backup_thread.join().unwrap();
     }
     if let Some(timer_thread) = timer_thread {
         timer_thread.join().unwrap();
     }
+    if let Some(control_thread) = control_thread {
+        control_thread.join().unwrap();
+    }
     input_thread.join().unwrap();
 
     // Final save
     let mut buffer = buffer.lock().unwrap();
-    dbg!(&buffer);
-    buffer.save()?;
+    let written = buffer.modified();
+    if let Err(err) = buffer.save() {
+        // Keep the text somewhere, e.g. when the encoding of the file can't hold it
+        let pending = buffer.pending_save();
+        let backup = match &config.snapshots {
+            Some(snapshots) => snapshots.of(buffer.path()).and_then(|snapshots| {
+                snapshots.save(&Local::now(), |snapshot| pending.write_copy(snapshot))
+            }),
+            None => pending
+                .write_backup()
+                .map(|()| pending.backup_path().to_path_buf()),
+        };
+        // The hook may print to the terminal too
+        restore_terminal(&mut term);
+        match backup {
+            Ok(path) => eprintln!("Saved your text to {:?} instead", path),
+            Err(backup_err) => eprintln!("Failed to save backup: {}", backup_err),
+        }
+        let context = HookContext::new(&buffer).with_error(&err);
+        if let Err(hook_err) = config.hooks.run(HookEvent::SaveError, &context) {
+            eprintln!("{}", hook_err);
+        }
+        return Err(err.into());
+    }
+    // Reported once the terminal is restored
+    let mut hook_errors = Vec::new();
+    let mut snapshot_error = None;
+    if written {
+        let context = HookContext::new(&buffer);
+        hook_errors.extend(config.hooks.run(HookEvent::Save, &context).err());
+        // The last state of the session is snapshotted too, not only autosaves
+        if let Some(snapshots) = &config.snapshots {
+            snapshot_error = snapshots
+                .of(buffer.path())
+                .and_then(|snapshots| snapshots.take(buffer.path(), &Local::now()))
+                .err();
+        }
+    }
+    let commit_message = config
+        .git_commit
+        .then(|| buffer.commit_message(&config.git_message));
+    // Reopen the project at the chapter it was left at
+    let project_error = session
+        .project()
+        .and_then(|project| project.save_current().err());
+    // Watchers see the stream end, once the text is safe
+    if let Some((broadcaster, broadcast_thread)) = broadcast {
+        drop(broadcaster);
+        broadcast_thread.join().unwrap();
+    }
 
     // Terminal cleanup
+    restore_terminal(&mut term);
+
+    // Final output for user
+    // Switching chapters of a project opens another file in the buffer
+    println!("Saved file to {:?}", buffer.path());
+    if let Some(path) = buffer.backup_path() {
+        eprintln!(
+            "Saving failed during the session, the text was kept in {:?}",
+            path
+        );
+    }
+    if let Some(err) = project_error {
+        eprintln!("Failed to record current chapter: {}", err);
+    }
+    if let Some(err) = snapshot_error {
+        eprintln!("Failed to save snapshot: {}", err);
+    }
+    if let Some(message) = commit_message {
+        match commit_file(buffer.path(), &message) {
+            Ok(true) => println!("Committed: {}", message),
+            Ok(false) => println!("No changes to commit"),
+            Err(err) => eprintln!("Failed to commit file: {}", err),
+        }
+    }
+    println!("{}", buffer.stats().summary());
+    let context = HookContext::new(&buffer);
+    hook_errors.extend(config.hooks.run(HookEvent::Exit, &context).err());
+    for err in hook_errors {
+        eprintln!("{}", err);
+    }
+
+    // Record session in history
+    if let Some(history_path) = &config.history_path {
+        let session_words = buffer.session_word_count();
+        let record = SessionRecord {
+            date: Local::now(),
+            file: buffer.path().to_path_buf(),
+            duration_secs: buffer.stats().elapsed().as_secs(),
+            words: session_words,
+            mode: config.writing_mode.to_string(),
+            goal_met: config.word_goal.map(|goal| session_words >= goal),
+            prompt,
+        };
+        if let Err(err) = append_record(history_path, &record) {
+            eprintln!("Failed to record session in {:?}: {}", history_path, err);
+        }
+    }
+
+    Ok(())
+}
+
+/// Leave the interface of a session, so output is printed to the terminal as usual
+fn restore_terminal(term: &mut Terminal<CrosstermBackend<io::Stdout>>) {
     term.show_cursor().unwrap();
     disable_raw_mode().unwrap();
     crossterm::execute!(
@@ -188,11 +602,4 @@ fn run(config: &Config) -> Result<(), Box<dyn Error>> {
         DisableMouseCapture
     )
     .unwrap();
-
-    dbg!(&buffer);
-
-    // Final output for user
-    println!("Saved file to {:?}", config.get_output_path());
-
-    Ok(())
 }
@@ -0,0 +1,302 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::file_format::FileFormat;
+
+/// Separator between chapters when building a project, unless set in the manifest
+const DEFAULT_SEPARATOR: &str = "\n\n";
+
+/// Manifest of a project written in several files, e.g. a book, as written in a YAML file.
+/// Paths are relative to the manifest. It is only read, never written, so it keeps its comments
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Manifest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Chapter files, in order
+    pub chapters: Vec<PathBuf>,
+    /// Text between chapters when building the project
+    /// default: a blank line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub separator: Option<String>,
+    /// File the project is built into
+    /// default: the manifest path with a .txt extension
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Error, Debug)]
+pub enum ProjectError {
+    #[error("Failed to read project: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("Failed to parse project manifest: {0}")]
+    InvalidManifest(#[from] serde_yaml::Error),
+
+    #[error("Project manifest lists no chapters")]
+    NoChapters,
+
+    #[error("Failed to save project state: {0}")]
+    InvalidState(#[from] serde_json::Error),
+}
+
+/// Where hemm left off in a project, kept apart from the manifest
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+struct ProjectState {
+    /// Chapter that was last written in, as listed in the manifest
+    current: Option<PathBuf>,
+}
+
+/// Words in a chapter of a project
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ChapterWords {
+    /// Chapter file, as listed in the manifest
+    pub file: PathBuf,
+    pub words: usize,
+}
+
+/// Word counts of a project, printed by `hemm project stats`
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ProjectSummary {
+    pub title: Option<String>,
+    pub chapters: Vec<ChapterWords>,
+    pub total: usize,
+}
+
+impl fmt::Display for ProjectSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(title) = &self.title {
+            writeln!(f, "{}", title)?;
+        }
+        for (index, chapter) in self.chapters.iter().enumerate() {
+            writeln!(
+                f,
+                "  {:>3}. {:<40} {:>7} words",
+                index + 1,
+                chapter.file.display(),
+                chapter.words
+            )?;
+        }
+        writeln!(f, "Total words: {}", self.total)
+    }
+}
+
+/// A project opened from its manifest
+#[derive(Debug, Clone)]
+pub struct Project {
+    manifest_path: PathBuf,
+    manifest: Manifest,
+    /// Index of the current chapter
+    current: usize,
+    /// File the current chapter is recorded in. If None, it isn't recorded
+    state_path: Option<PathBuf>,
+}
+
+impl Project {
+    pub fn load(manifest_path: &Path) -> Result<Self, ProjectError> {
+        let manifest: Manifest = serde_yaml::from_str(&fs::read_to_string(manifest_path)?)?;
+        if manifest.chapters.is_empty() {
+            return Err(ProjectError::NoChapters);
+        }
+        Ok(Self {
+            manifest_path: manifest_path.to_path_buf(),
+            manifest,
+            current: 0,
+            state_path: None,
+        })
+    }
+
+    /// Continue at the chapter recorded in the state file at path, and record it there from now on.
+    /// A chapter no longer in the manifest is ignored, opening the first chapter instead
+    pub fn with_state(mut self, state_path: PathBuf) -> Result<Self, ProjectError> {
+        let state: ProjectState = match fs::read_to_string(&state_path) {
+            Ok(data) => serde_json::from_str(&data).unwrap_or_default(),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => ProjectState::default(),
+            Err(err) => return Err(err.into()),
+        };
+        if let Some(current) = state.current {
+            self.current = self
+                .manifest
+                .chapters
+                .iter()
+                .position(|chapter| *chapter == current)
+                .unwrap_or(0);
+        }
+        self.state_path = Some(state_path);
+        Ok(self)
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.manifest.title.as_deref()
+    }
+
+    /// Resolve path in the manifest against the manifest's directory
+    fn resolve(&self, path: &Path) -> PathBuf {
+        match self.manifest_path.parent() {
+            Some(dir) => dir.join(path),
+            None => path.to_path_buf(),
+        }
+    }
+
+    /// Number of chapters
+    pub fn chapter_count(&self) -> usize {
+        self.manifest.chapters.len()
+    }
+
+    /// Path of chapter at index
+    pub fn chapter_path(&self, index: usize) -> PathBuf {
+        self.resolve(&self.manifest.chapters[index])
+    }
+
+    /// Index of the current chapter
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    /// Make chapter at index the current chapter, kept within the chapters
+    pub fn set_current(&mut self, index: usize) {
+        self.current = index.min(self.chapter_count() - 1);
+    }
+
+    /// Record the current chapter in the state file, to continue there next time
+    pub fn save_current(&self) -> Result<(), ProjectError> {
+        let Some(state_path) = &self.state_path else {
+            return Ok(());
+        };
+        let state = ProjectState {
+            current: Some(self.manifest.chapters[self.current].clone()),
+        };
+        if let Some(dir) = state_path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(state_path, serde_json::to_string(&state)?)?;
+        Ok(())
+    }
+
+    /// Words in each chapter. Chapters that don't exist yet have none
+    pub fn summary(&self) -> io::Result<ProjectSummary> {
+        let mut chapters = Vec::new();
+        for (index, file) in self.manifest.chapters.iter().enumerate() {
+            let words = self.read_chapter(index)?.split_whitespace().count();
+            chapters.push(ChapterWords {
+                file: file.clone(),
+                words,
+            });
+        }
+        Ok(ProjectSummary {
+            title: self.manifest.title.clone(),
+            total: chapters.iter().map(|chapter| chapter.words).sum(),
+            chapters,
+        })
+    }
+
+    /// Text of chapter at index with `\n` line endings, empty if it doesn't exist yet
+    fn read_chapter(&self, index: usize) -> io::Result<String> {
+        match fs::read(self.chapter_path(index)) {
+            Ok(data) => Ok(FileFormat::decode(&data)
+                .0
+                .lines()
+                .collect::<Vec<_>>()
+                .join("\n")),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// All chapters in order, joined by the separator. Empty chapters are skipped
+    pub fn build(&self) -> io::Result<String> {
+        let separator = self
+            .manifest
+            .separator
+            .as_deref()
+            .unwrap_or(DEFAULT_SEPARATOR);
+        let mut chapters = Vec::new();
+        for index in 0..self.chapter_count() {
+            let text = self.read_chapter(index)?;
+            let text = text.trim_end();
+            if !text.is_empty() {
+                chapters.push(text.to_string());
+            }
+        }
+        Ok(chapters.join(separator) + "\n")
+    }
+
+    /// File the project is built into
+    pub fn output_path(&self) -> PathBuf {
+        match &self.manifest.output {
+            Some(output) => self.resolve(output),
+            None => self.manifest_path.with_extension("txt"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_and_builds_chapters() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest_path = dir.path().join("book.yaml");
+        fs::write(
+            &manifest_path,
+            "title: Rain\nchapters:\n  - one.txt\n  - two.txt\n  - three.txt\nseparator: \"\\n\\n* * *\\n\\n\"\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("one.txt"), "It rained.\n").unwrap();
+        fs::write(
+            dir.path().join("two.txt"),
+            "It rained again,\r\nall day.\r\n",
+        )
+        .unwrap();
+
+        let project = Project::load(&manifest_path).unwrap();
+        assert_eq!(project.current(), 0);
+        let summary = project.summary().unwrap();
+        let words: Vec<_> = summary.chapters.iter().map(|c| c.words).collect();
+        assert_eq!(words, [2, 5, 0]);
+        assert_eq!(summary.total, 7);
+        assert_eq!(
+            project.build().unwrap(),
+            "It rained.\n\n* * *\n\nIt rained again,\nall day.\n"
+        );
+        assert_eq!(project.output_path(), dir.path().join("book.txt"));
+    }
+
+    #[test]
+    fn remembers_current_chapter() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest_path = dir.path().join("book.yaml");
+        let manifest = "# Chapters in order\nchapters: [one.txt, two.txt]\n";
+        fs::write(&manifest_path, manifest).unwrap();
+        let state_path = dir.path().join("state").join("book.json");
+        let open = || {
+            Project::load(&manifest_path)
+                .unwrap()
+                .with_state(state_path.clone())
+                .unwrap()
+        };
+
+        let mut project = open();
+        assert_eq!(project.current(), 0);
+        project.set_current(5);
+        assert_eq!(project.current(), 1);
+        project.save_current().unwrap();
+
+        let project = open();
+        assert_eq!(
+            project.chapter_path(project.current()),
+            dir.path().join("two.txt")
+        );
+        // The manifest is left as the user wrote it
+        assert_eq!(fs::read_to_string(&manifest_path).unwrap(), manifest);
+
+        // Chapters removed from the manifest are forgotten
+        fs::write(&manifest_path, "chapters: [one.txt]\n").unwrap();
+        assert_eq!(open().current(), 0);
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::hash_map::RandomState;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::path::Path;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Where the prompt of a session is shown
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PromptPlacement {
+    /// Above the text, without being saved to the file
+    #[default]
+    Above,
+    /// Inserted into the text as a header
+    Header,
+}
+
+/// A writing prompt, with tags to pick prompts by
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Prompt {
+    pub text: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// Prompt as written in a YAML prompt file, either plain text or with tags
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PromptEntry {
+    Text(String),
+    Tagged(Prompt),
+}
+
+#[derive(Error, Debug)]
+pub enum PromptError {
+    #[error("Failed to read prompt file: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("Failed to parse prompt file: {0}")]
+    InvalidYaml(#[from] serde_yaml::Error),
+
+    #[error("No prompts tagged {0:?} in prompt file")]
+    NoTaggedPrompts(String),
+
+    #[error("Prompt file has no prompts")]
+    NoPrompts,
+}
+
+/// Prompts loaded from the user's prompt file
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromptLibrary {
+    prompts: Vec<Prompt>,
+}
+
+impl PromptLibrary {
+    /// Load prompts from a YAML list if the file name ends in `.yaml` or `.yml`,
+    /// or from a text file with one prompt per line otherwise
+    pub fn load(path: &Path) -> Result<Self, PromptError> {
+        let contents = fs::read_to_string(path)?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("yaml" | "yml") => Self::from_yaml(&contents),
+            _ => Ok(Self::from_lines(&contents)),
+        }
+    }
+
+    /// One prompt per line. Blank lines and lines starting with `#` are skipped
+    pub fn from_lines(contents: &str) -> Self {
+        let prompts = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| Prompt {
+                text: String::from(line),
+                tags: Vec::new(),
+            })
+            .collect();
+        Self { prompts }
+    }
+
+    /// A list of prompts, each either a string or a map with `text` and `tags`
+    pub fn from_yaml(contents: &str) -> Result<Self, PromptError> {
+        let entries: Vec<PromptEntry> = serde_yaml::from_str(contents)?;
+        let prompts = entries
+            .into_iter()
+            .map(|entry| match entry {
+                PromptEntry::Text(text) => Prompt {
+                    text,
+                    tags: Vec::new(),
+                },
+                PromptEntry::Tagged(prompt) => prompt,
+            })
+            .collect();
+        Ok(Self { prompts })
+    }
+
+    /// Prompts with tag, or all prompts if tag is None
+    pub fn matching(&self, tag: Option<&str>) -> Vec<&Prompt> {
+        self.prompts
+            .iter()
+            .filter(|prompt| tag.map_or(true, |tag| prompt.tags.iter().any(|t| t == tag)))
+            .collect()
+    }
+
+    /// Pick a random prompt with tag, or from all prompts if tag is None
+    pub fn pick(&self, tag: Option<&str>) -> Result<&Prompt, PromptError> {
+        let prompts = self.matching(tag);
+        if prompts.is_empty() {
+            return Err(match tag {
+                Some(tag) => PromptError::NoTaggedPrompts(String::from(tag)),
+                None => PromptError::NoPrompts,
+            });
+        }
+        // Randomly seeded by the standard library, good enough to pick a prompt
+        let random = RandomState::new().build_hasher().finish();
+        Ok(prompts[(random % prompts.len() as u64) as usize])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_prompts_from_lines_and_yaml() {
+        let library = PromptLibrary::from_lines(
+            "# Morning pages\nWhat woke you up?\n\n  The smell of rain  \n",
+        );
+        let texts: Vec<_> = library.matching(None).iter().map(|p| &p.text).collect();
+        assert_eq!(texts, ["What woke you up?", "The smell of rain"]);
+
+        let library = PromptLibrary::from_yaml(
+            "- Describe your street\n- text: A letter to your younger self\n  tags: [memoir]\n",
+        )
+        .unwrap();
+        assert_eq!(library.matching(None).len(), 2);
+        assert_eq!(
+            library.pick(Some("memoir")).unwrap().text,
+            "A letter to your younger self"
+        );
+        assert!(matches!(
+            library.pick(Some("poetry")),
+            Err(PromptError::NoTaggedPrompts(_))
+        ));
+    }
+}
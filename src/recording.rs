@@ -0,0 +1,255 @@
+use std::fs::{self, File};
+use std::io::{self, LineWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local};
+use crossterm::event::KeyCode;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+// Recording format, version 1:
+//
+// A JSON Lines file. The first line is a header, holding the text in the editor when recording
+// started:
+//
+//   {"format":"hemm-recording","version":1,"started":"2026-10-19T08:00:00+02:00",
+//    "file":"/home/me/diary.txt","mode":"hemingway","text":"Existing text\n"}
+//
+// The file the session was written to may be missing.
+//
+// Every following line is a key press, with the milliseconds since recording started:
+//
+//   {"t":1520,"key":"H"}
+//   {"t":1610,"key":"Enter"}
+//
+// Keys that type a character are that character. Other keys are one of Enter, Tab, Backspace,
+// Delete, Left, Right, Up, Down, Home, End, PageUp, PageDown or Esc.
+// Readers must reject recordings with a version they don't know.
+
+/// Identifies a hemm recording
+const FORMAT: &str = "hemm-recording";
+/// Version of the recording format written
+pub const VERSION: u32 = 1;
+
+#[derive(Error, Debug)]
+pub enum RecordingError {
+    #[error("Failed to read recording: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("Line {line} of recording is invalid: {error}")]
+    InvalidLine {
+        line: usize,
+        error: serde_json::Error,
+    },
+
+    #[error("File is not a hemm recording")]
+    NotARecording,
+
+    #[error("Recording format version {0} is not supported, the latest is {VERSION}")]
+    UnsupportedVersion(u32),
+}
+
+/// First line of a recording
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RecordingHeader {
+    pub format: String,
+    pub version: u32,
+    pub started: DateTime<Local>,
+    /// File the session was written to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+    /// Writing mode, which decides what keys do
+    pub mode: String,
+    /// Text in the editor when recording started
+    pub text: String,
+}
+
+/// A key press in a recording
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RecordedKey {
+    /// Milliseconds since recording started
+    pub t: u64,
+    pub key: String,
+}
+
+impl RecordedKey {
+    /// Time since recording started
+    pub fn time(&self) -> Duration {
+        Duration::from_millis(self.t)
+    }
+
+    /// The key as a crossterm key code, if it is one that hemm handles
+    pub fn code(&self) -> Option<KeyCode> {
+        let mut chars = self.key.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            return Some(KeyCode::Char(c));
+        }
+        Some(match self.key.as_str() {
+            "Enter" => KeyCode::Enter,
+            "Tab" => KeyCode::Tab,
+            "Backspace" => KeyCode::Backspace,
+            "Delete" => KeyCode::Delete,
+            "Left" => KeyCode::Left,
+            "Right" => KeyCode::Right,
+            "Up" => KeyCode::Up,
+            "Down" => KeyCode::Down,
+            "Home" => KeyCode::Home,
+            "End" => KeyCode::End,
+            "PageUp" => KeyCode::PageUp,
+            "PageDown" => KeyCode::PageDown,
+            "Esc" => KeyCode::Esc,
+            _ => return None,
+        })
+    }
+}
+
+/// Name of key in recordings, if it is one that hemm handles
+fn key_name(code: KeyCode) -> Option<String> {
+    Some(String::from(match code {
+        KeyCode::Char(c) => return Some(c.to_string()),
+        KeyCode::Enter => "Enter",
+        KeyCode::Tab => "Tab",
+        KeyCode::Backspace => "Backspace",
+        KeyCode::Delete => "Delete",
+        KeyCode::Left => "Left",
+        KeyCode::Right => "Right",
+        KeyCode::Up => "Up",
+        KeyCode::Down => "Down",
+        KeyCode::Home => "Home",
+        KeyCode::End => "End",
+        KeyCode::PageUp => "PageUp",
+        KeyCode::PageDown => "PageDown",
+        KeyCode::Esc => "Esc",
+        _ => return None,
+    }))
+}
+
+/// Writes key presses to a recording as they happen
+pub struct Recorder {
+    /// Flushed after every line, so the recording survives a crash
+    file: LineWriter<File>,
+    started: Instant,
+}
+
+impl Recorder {
+    /// Start a new recording at path of a session writing to file, replacing any existing recording
+    pub fn create(path: &Path, file: &Path, mode: &str, text: &str) -> io::Result<Self> {
+        let mut recorder = Self {
+            file: LineWriter::new(File::create(path)?),
+            started: Instant::now(),
+        };
+        let header = RecordingHeader {
+            format: String::from(FORMAT),
+            version: VERSION,
+            started: Local::now(),
+            file: Some(file.to_path_buf()),
+            mode: String::from(mode),
+            text: String::from(text),
+        };
+        recorder.write_line(&header)?;
+        Ok(recorder)
+    }
+
+    /// Record key press. Keys that hemm doesn't handle are skipped
+    pub fn record(&mut self, code: KeyCode) -> io::Result<()> {
+        let Some(key) = key_name(code) else {
+            return Ok(());
+        };
+        let t = self.started.elapsed().as_millis() as u64;
+        self.write_line(&RecordedKey { t, key })
+    }
+
+    fn write_line<T: Serialize>(&mut self, value: &T) -> io::Result<()> {
+        let line = serde_json::to_string(value)?;
+        writeln!(self.file, "{}", line)
+    }
+}
+
+/// A recording loaded for replay
+#[derive(Debug)]
+pub struct Recording {
+    pub path: PathBuf,
+    pub header: RecordingHeader,
+    pub keys: Vec<RecordedKey>,
+}
+
+impl Recording {
+    pub fn load(path: &Path) -> Result<Self, RecordingError> {
+        let contents = fs::read_to_string(path)?;
+        let mut lines = contents.lines().enumerate();
+        let (_, first_line) = lines.next().ok_or(RecordingError::NotARecording)?;
+        let header: RecordingHeader =
+            serde_json::from_str(first_line).map_err(|_| RecordingError::NotARecording)?;
+        if header.format != FORMAT {
+            return Err(RecordingError::NotARecording);
+        }
+        if header.version != VERSION {
+            return Err(RecordingError::UnsupportedVersion(header.version));
+        }
+        let keys = lines
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                serde_json::from_str(line).map_err(|error| RecordingError::InvalidLine {
+                    line: index + 1,
+                    error,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            header,
+            keys,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_and_loads_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.log");
+        let file = dir.path().join("diary.txt");
+        let mut recorder = Recorder::create(&path, &file, "hemingway", "Dear diary\n").unwrap();
+        for code in [
+            KeyCode::Char('H'),
+            KeyCode::Enter,
+            KeyCode::F(1),
+            KeyCode::Esc,
+        ] {
+            recorder.record(code).unwrap();
+        }
+
+        let recording = Recording::load(&path).unwrap();
+        assert_eq!(recording.header.file, Some(file));
+        assert_eq!(recording.header.mode, "hemingway");
+        assert_eq!(recording.header.text, "Dear diary\n");
+        let codes: Vec<_> = recording.keys.iter().map(|key| key.code()).collect();
+        assert_eq!(
+            codes,
+            [
+                Some(KeyCode::Char('H')),
+                Some(KeyCode::Enter),
+                Some(KeyCode::Esc)
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.log");
+        fs::write(
+            &path,
+            r#"{"format":"hemm-recording","version":99,"started":"2026-10-19T08:00:00+02:00","mode":"regular","text":""}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            Recording::load(&path),
+            Err(RecordingError::UnsupportedVersion(99))
+        ));
+    }
+}
@@ -0,0 +1,232 @@
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crossterm::event::KeyEvent;
+
+use crate::buffer::Buffer;
+use crate::config::Config;
+use crate::event::AppEvent;
+use crate::history::Streak;
+use crate::input::{chapter_offset, handle_key, type_text};
+use crate::project::Project;
+use crate::prompt::PromptPlacement;
+use crate::status::{render_status, StatusFields};
+
+/// An editing session, independent of the terminal.
+/// The terminal front-end feeds it events from the other threads and draws its state,
+/// and scripts and tests can drive it directly with key events
+pub struct Session<'a> {
+    config: &'a Config,
+    /// Shared with the autosave and input threads
+    buffer: Arc<Mutex<Buffer>>,
+    /// Streak from session history, if enabled
+    streak: Option<Streak>,
+    /// Time since the session started, as last reported by the timer
+    elapsed: Duration,
+    /// Time added to the timer duration since the session started
+    timer_extension: Duration,
+    /// Writing prompt shown above the text
+    prompt: Option<String>,
+    /// Project the buffer holds a chapter of, if any
+    project: Option<Project>,
+    /// Words in each chapter of the project, as of when they were last open
+    chapter_words: Vec<usize>,
+    /// Set when the user asked to exit
+    finished: bool,
+}
+
+impl<'a> Session<'a> {
+    /// Open the output file of config
+    pub fn new(config: &'a Config) -> io::Result<Self> {
+        Ok(Self::with_buffer(config, Buffer::new(config)?))
+    }
+
+    /// Session for an already opened buffer, e.g. of an encrypted file
+    pub fn with_buffer(config: &'a Config, buffer: Buffer) -> Self {
+        Self {
+            config,
+            buffer: Arc::new(Mutex::new(buffer)),
+            streak: None,
+            elapsed: Duration::default(),
+            timer_extension: Duration::default(),
+            prompt: None,
+            project: None,
+            chapter_words: Vec::new(),
+            finished: false,
+        }
+    }
+
+    /// Show streak in the status line, and announce it
+    pub fn set_streak(&mut self, streak: Streak) {
+        let message = format!("Streak: {} days", streak.current);
+        self.buffer.lock().unwrap().set_message(Some(message));
+        self.streak = Some(streak);
+    }
+
+    /// Start the session with a writing prompt, shown above the text or inserted into it
+    pub fn set_prompt(&mut self, prompt: &str, placement: PromptPlacement) {
+        match placement {
+            PromptPlacement::Above => self.prompt = Some(String::from(prompt)),
+            PromptPlacement::Header => self.buffer.lock().unwrap().insert_header(prompt),
+        }
+    }
+
+    /// Prompt shown above the text, if any
+    pub fn prompt(&self) -> Option<&str> {
+        self.prompt.as_deref()
+    }
+
+    /// Write a project, whose current chapter is open in the buffer
+    pub fn set_project(&mut self, project: Project) -> io::Result<()> {
+        self.chapter_words = project
+            .summary()?
+            .chapters
+            .iter()
+            .map(|chapter| chapter.words)
+            .collect();
+        self.project = Some(project);
+        Ok(())
+    }
+
+    pub fn project(&self) -> Option<&Project> {
+        self.project.as_ref()
+    }
+
+    /// Save the current chapter, and open the chapter offset chapters away from it.
+    /// Failures are shown as a message, and leave the current chapter open
+    pub fn switch_chapter(&mut self, offset: isize) {
+        let Some(project) = self.project.as_mut() else {
+            return;
+        };
+        let current = project.current();
+        let index = current
+            .saturating_add_signed(offset)
+            .min(project.chapter_count() - 1);
+        if index == current {
+            return;
+        }
+        let mut buffer = self.buffer.lock().unwrap();
+        // An autosave of older text still being written can't overwrite this, its writer skips it
+        if let Err(err) = buffer.save() {
+            buffer.set_message(Some(format!("Failed to save chapter: {}", err)));
+            return;
+        }
+        let path = project.chapter_path(index);
+        let mut chapter = match Buffer::open_path(self.config, &path) {
+            Ok(chapter) => chapter,
+            Err(err) => {
+                buffer.set_message(Some(format!("Failed to open chapter: {}", err)));
+                return;
+            }
+        };
+        self.chapter_words[current] = buffer.word_count();
+        project.set_current(index);
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        chapter.set_message(Some(format!(
+            "Chapter {}/{}: {}",
+            index + 1,
+            project.chapter_count(),
+            name
+        )));
+        let previous = std::mem::replace(&mut *buffer, chapter);
+        buffer.continue_session(previous);
+    }
+
+    pub fn config(&self) -> &'a Config {
+        self.config
+    }
+
+    /// Buffer shared with other threads
+    pub fn buffer(&self) -> Arc<Mutex<Buffer>> {
+        Arc::clone(&self.buffer)
+    }
+
+    /// Apply key press to the buffer, as the input thread does
+    pub fn handle_key(&mut self, key: KeyEvent) {
+        let restricted = self.config.writing_mode.is_restricted();
+        if let Some(offset) = chapter_offset(key) {
+            if !restricted {
+                self.switch_chapter(offset);
+            }
+            return;
+        }
+        if handle_key(&mut self.buffer.lock().unwrap(), key, restricted) {
+            self.finished = true;
+        }
+    }
+
+    /// Type text one key at a time. Line breaks are typed as Enter
+    pub fn type_text(&mut self, text: &str) {
+        let restricted = self.config.writing_mode.is_restricted();
+        type_text(&mut self.buffer.lock().unwrap(), text, restricted);
+    }
+
+    /// Update state from an event sent by another thread
+    pub fn handle_event(&mut self, event: AppEvent) {
+        match event {
+            AppEvent::Tick(elapsed) => self.elapsed = elapsed,
+            AppEvent::SaveResult(Err(message)) | AppEvent::Message(message) => {
+                self.buffer.lock().unwrap().set_message(Some(message))
+            }
+            AppEvent::ExtendTimer(extension) => {
+                self.timer_extension = self.timer_extension.saturating_add(extension)
+            }
+            AppEvent::SwitchChapter(offset) => self.switch_chapter(offset),
+            AppEvent::Quit => self.finished = true,
+            AppEvent::Input | AppEvent::Resize | AppEvent::SaveResult(Ok(())) => {}
+        }
+    }
+
+    /// Whether the user asked to exit
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Time since the session started, as last reported by the timer
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Text in the editor, with lines joined by `\n`
+    pub fn text(&self) -> String {
+        self.buffer.lock().unwrap().textarea.lines().join("\n")
+    }
+
+    /// Right side of the status line
+    pub fn status(&self) -> String {
+        let buffer = self.buffer.lock().unwrap();
+        let fields = StatusFields {
+            words: buffer.word_count(),
+            chars: buffer.char_count(),
+            session_words: buffer.session_word_count(),
+            elapsed: self.elapsed,
+            duration: self
+                .config
+                .timer_duration
+                .map(|duration| duration.saturating_add(self.timer_extension)),
+            saved: !buffer.modified(),
+            mode: &self.config.writing_mode,
+            word_goal: self.config.word_goal,
+            stats: buffer.stats(),
+            streak: self
+                .streak
+                .map(|streak| streak.with_session(buffer.session_word_count())),
+            chapter: self
+                .project
+                .as_ref()
+                .map(|project| (project.current(), project.chapter_count())),
+            project_words: self.project.as_ref().map(|project| {
+                let other_chapters: usize = self.chapter_words.iter().sum::<usize>()
+                    - self.chapter_words[project.current()];
+                other_chapters + buffer.word_count()
+            }),
+        };
+        render_status(&self.config.status_template, &fields)
+    }
+
+    /// Save to the output file, if modified
+    pub fn save(&self) -> io::Result<()> {
+        self.buffer.lock().unwrap().save()
+    }
+}
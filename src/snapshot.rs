@@ -0,0 +1,239 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// How often a new snapshot is started. Within the same period, the snapshot is updated
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SnapshotInterval {
+    /// A new snapshot on every save
+    Save,
+    /// One snapshot per hour
+    Hour,
+    /// One snapshot per day
+    Day,
+}
+
+impl SnapshotInterval {
+    /// Snapshot file name for a save at `now`. Names sort in chronological order
+    fn file_name(&self, now: &DateTime<Local>) -> String {
+        let format = match self {
+            SnapshotInterval::Save => "%Y%m%d-%H%M%S",
+            SnapshotInterval::Hour => "%Y%m%d-%H",
+            SnapshotInterval::Day => "%Y%m%d",
+        };
+        now.format(format).to_string()
+    }
+}
+
+/// Absolute path, without resolving symlinks so it works for files that don't exist
+fn absolute(path: &Path) -> io::Result<PathBuf> {
+    let path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        env::current_dir()?.join(path)
+    };
+    // Collecting components drops `.` components
+    Ok(path.components().collect())
+}
+
+/// Rotating snapshots of one file, stored in their own directory
+#[derive(Debug, Clone)]
+pub struct Snapshots {
+    /// Directory holding the snapshot directories of all files
+    root: PathBuf,
+    /// Directory holding snapshots of this file only
+    dir: PathBuf,
+    interval: SnapshotInterval,
+    /// Number of snapshots to keep, oldest are removed first. At least one
+    keep: usize,
+}
+
+impl Snapshots {
+    /// Snapshots of `file`, stored under `root`.
+    /// Each file gets a directory named after its absolute path, like `%home%me%notes.txt`
+    pub fn new(
+        root: &Path,
+        file: &Path,
+        interval: SnapshotInterval,
+        keep: usize,
+    ) -> io::Result<Self> {
+        let file = absolute(file)?;
+        let dir_name = file.to_string_lossy().replace(['/', '\\', ':'], "%");
+        Ok(Self {
+            root: root.to_path_buf(),
+            dir: root.join(dir_name),
+            interval,
+            // The snapshot just taken is always kept
+            keep: keep.max(1),
+        })
+    }
+
+    /// Snapshots of another file, kept the same way. Chapters of a project each have their own
+    pub fn of(&self, file: &Path) -> io::Result<Self> {
+        Self::new(&self.root, file, self.interval, self.keep)
+    }
+
+    /// Copy current contents of file to a snapshot, and remove old snapshots
+    pub fn take(&self, file: &Path, now: &DateTime<Local>) -> io::Result<PathBuf> {
+        self.save(now, |snapshot| fs::copy(file, snapshot).map(|_| ()))
+    }
+
+    /// Write a snapshot with `write`, given the path of the snapshot, and remove old snapshots.
+    /// Used to keep text that failed to save to the file itself
+    pub fn save(
+        &self,
+        now: &DateTime<Local>,
+        write: impl FnOnce(&Path) -> io::Result<()>,
+    ) -> io::Result<PathBuf> {
+        fs::create_dir_all(&self.dir)?;
+        let snapshot = self.dir.join(self.interval.file_name(now));
+        write(&snapshot)?;
+        self.prune()?;
+        Ok(snapshot)
+    }
+
+    /// Snapshots of file, oldest first
+    pub fn list(&self) -> io::Result<Vec<PathBuf>> {
+        if !self.dir.is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut snapshots: Vec<PathBuf> = fs::read_dir(&self.dir)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .collect();
+        snapshots.sort();
+        Ok(snapshots)
+    }
+
+    /// Find snapshot by name, or the most recent one if no name is given
+    pub fn find(&self, name: Option<&str>) -> io::Result<Option<PathBuf>> {
+        let snapshots = self.list()?;
+        Ok(match name {
+            Some(name) => snapshots.into_iter().find(|path| {
+                path.file_name()
+                    .map_or(false, |file_name| file_name == name)
+            }),
+            None => snapshots.into_iter().last(),
+        })
+    }
+
+    /// Replace contents of file with snapshot.
+    /// The current contents are snapshotted first, so a restore can be undone
+    pub fn restore(&self, file: &Path, snapshot: &Path, now: &DateTime<Local>) -> io::Result<()> {
+        if file.is_file() {
+            let current = self
+                .dir
+                .join(format!("{}-before-restore", now.format("%Y%m%d-%H%M%S")));
+            fs::create_dir_all(&self.dir)?;
+            fs::copy(file, current)?;
+        }
+        fs::copy(snapshot, file)?;
+        Ok(())
+    }
+
+    fn prune(&self) -> io::Result<()> {
+        let snapshots = self.list()?;
+        let excess = snapshots.len().saturating_sub(self.keep);
+        for snapshot in &snapshots[..excess] {
+            fs::remove_file(snapshot)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn at(hour: u32, min: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 3, 6, hour, min, 0).unwrap()
+    }
+
+    fn names(snapshots: &Snapshots) -> Vec<String> {
+        snapshots
+            .list()
+            .unwrap()
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn keeps_one_snapshot_per_interval() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("notes.txt");
+        let root = dir.path().join("snapshots");
+
+        let hourly = Snapshots::new(&root, &file, SnapshotInterval::Hour, 2).unwrap();
+        for (hour, min, text) in [(7, 10, "a"), (7, 50, "ab"), (8, 5, "abc"), (9, 0, "abcd")] {
+            fs::write(&file, text).unwrap();
+            hourly.take(&file, &at(hour, min)).unwrap();
+        }
+        // Saves within the hour update its snapshot, and only the last two are kept
+        assert_eq!(names(&hourly), ["20240306-08", "20240306-09"]);
+        let latest = hourly.find(None).unwrap().unwrap();
+        assert_eq!(fs::read_to_string(latest).unwrap(), "abcd");
+
+        let daily =
+            Snapshots::new(&root, &dir.path().join("log.txt"), SnapshotInterval::Day, 5).unwrap();
+        daily.take(&file, &at(7, 0)).unwrap();
+        daily.take(&file, &at(23, 0)).unwrap();
+        assert_eq!(names(&daily), ["20240306"]);
+    }
+
+    #[test]
+    fn keeps_snapshot_just_taken() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("notes.txt");
+        fs::write(&file, "text").unwrap();
+        let snapshots = Snapshots::new(
+            &dir.path().join("snapshots"),
+            &file,
+            SnapshotInterval::Save,
+            0,
+        )
+        .unwrap();
+        snapshots.take(&file, &at(7, 0)).unwrap();
+        snapshots.take(&file, &at(7, 1)).unwrap();
+        assert_eq!(names(&snapshots), ["20240306-070100"]);
+    }
+
+    #[test]
+    fn restores_snapshot_keeping_current_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("notes.txt");
+        let snapshots = Snapshots::new(
+            &dir.path().join("snapshots"),
+            &file,
+            SnapshotInterval::Save,
+            10,
+        )
+        .unwrap();
+        fs::write(&file, "first").unwrap();
+        snapshots.take(&file, &at(7, 0)).unwrap();
+        fs::write(&file, "second").unwrap();
+
+        let snapshot = snapshots.find(Some("20240306-070000")).unwrap().unwrap();
+        assert!(snapshots.find(Some("20240306-080000")).unwrap().is_none());
+        snapshots.restore(&file, &snapshot, &at(8, 0)).unwrap();
+        assert_eq!(fs::read_to_string(&file).unwrap(), "first");
+        assert_eq!(
+            names(&snapshots),
+            ["20240306-070000", "20240306-080000-before-restore"]
+        );
+        let before = snapshots
+            .find(Some("20240306-080000-before-restore"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(fs::read_to_string(before).unwrap(), "second");
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use crate::status::format_duration;
+
+/// Length of the window used for rolling words per minute
+const ROLLING_WINDOW: Duration = Duration::from_secs(60);
+
+/// Statistics about the current writing session, updated on each keystroke
+#[derive(Debug)]
+pub struct SessionStats {
+    /// Start of the session
+    start: Instant,
+    /// Gaps between keystrokes longer than this are counted as pauses
+    pause_threshold: Duration,
+    /// Number of keys pressed
+    pub keystrokes: usize,
+    /// Number of words added
+    pub words_added: usize,
+    /// Number of words deleted
+    pub words_deleted: usize,
+    /// Number of pauses longer than the threshold
+    pub pauses: usize,
+    /// Longest gap between keystrokes
+    pub longest_pause: Duration,
+    /// Instant of last keystroke
+    last_keystroke: Option<Instant>,
+    /// Words added within the rolling window
+    recent_words: VecDeque<(Instant, usize)>,
+}
+
+impl SessionStats {
+    pub fn new(pause_threshold: Duration) -> Self {
+        Self {
+            start: Instant::now(),
+            pause_threshold,
+            keystrokes: 0,
+            words_added: 0,
+            words_deleted: 0,
+            pauses: 0,
+            longest_pause: Duration::default(),
+            last_keystroke: None,
+            recent_words: VecDeque::new(),
+        }
+    }
+
+    /// Record a keystroke that changed the word count from `words_before` to `words_after`
+    pub fn record_keystroke(&mut self, words_before: usize, words_after: usize) {
+        let now = Instant::now();
+        self.keystrokes += 1;
+
+        if let Some(last_keystroke) = self.last_keystroke {
+            let gap = now.duration_since(last_keystroke);
+            if gap >= self.pause_threshold {
+                self.pauses += 1;
+            }
+            self.longest_pause = self.longest_pause.max(gap);
+        }
+        self.last_keystroke = Some(now);
+
+        if words_after > words_before {
+            let added = words_after - words_before;
+            self.words_added += added;
+            self.recent_words.push_back((now, added));
+        } else {
+            self.words_deleted += words_before - words_after;
+        }
+        self.expire_recent_words(now);
+    }
+
+    fn expire_recent_words(&mut self, now: Instant) {
+        while let Some((instant, _)) = self.recent_words.front() {
+            if now.duration_since(*instant) <= ROLLING_WINDOW {
+                break;
+            }
+            self.recent_words.pop_front();
+        }
+    }
+
+    /// Time since the session started
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Words per minute over the last minute
+    pub fn rolling_wpm(&self) -> usize {
+        let now = Instant::now();
+        let words: usize = self
+            .recent_words
+            .iter()
+            .filter(|(instant, _)| now.duration_since(*instant) <= ROLLING_WINDOW)
+            .map(|(_, words)| words)
+            .sum();
+        // Early in the session, the window is only as long as the session
+        let window = self.elapsed().min(ROLLING_WINDOW).as_secs_f64();
+        if window < 1.0 {
+            return 0;
+        }
+        (words as f64 * 60.0 / window).round() as usize
+    }
+
+    /// Words per minute over the whole session
+    pub fn average_wpm(&self) -> usize {
+        let minutes = self.elapsed().as_secs_f64() / 60.0;
+        if minutes < 1.0 / 60.0 {
+            return 0;
+        }
+        (self.words_added.saturating_sub(self.words_deleted) as f64 / minutes).round() as usize
+    }
+
+    /// Summary printed after the session ends
+    pub fn summary(&self) -> String {
+        let mut summary = String::new();
+        // Writing to String cannot fail
+        let _ = writeln!(
+            summary,
+            "Session length: {}",
+            format_duration(self.elapsed())
+        );
+        let _ = writeln!(summary, "Keystrokes:     {}", self.keystrokes);
+        let _ = writeln!(summary, "Words added:    {}", self.words_added);
+        let _ = writeln!(summary, "Words deleted:  {}", self.words_deleted);
+        let _ = writeln!(summary, "Average WPM:    {}", self.average_wpm());
+        let _ = write!(
+            summary,
+            "Pauses:         {} (longest {})",
+            self.pauses,
+            format_duration(self.longest_pause)
+        );
+        summary
+    }
+}
@@ -0,0 +1,210 @@
+use std::time::Duration;
+
+use crate::config::WritingMode;
+use crate::stats::SessionStats;
+use crate::template::render_template;
+
+/// Template used for the status line when no template is given
+pub const DEFAULT_STATUS_TEMPLATE: &str = "{words} words";
+
+/// Template used for the status line when the timer is shown and no template is given
+pub const TIMER_STATUS_TEMPLATE: &str = "{elapsed}";
+
+/// Values available to the status line template.
+///
+/// Each field is referenced in the template by name in braces, e.g. `{words} words`
+/// - `{words}`: Words in the whole file
+/// - `{chars}`: Characters in the whole file
+/// - `{elapsed}`: Time since the session started
+/// - `{remaining}`: Time left of `--duration`, empty if no duration is set
+/// - `{saved}`: `saved` or `unsaved`
+/// - `{mode}`: Writing mode
+/// - `{wpm}`: Words per minute written this session
+/// - `{goal%}`: Percentage of `--goal` words written this session, empty if no goal is set
+/// - `{rwpm}`: Words per minute over the last minute
+/// - `{keystrokes}`: Keys pressed this session
+/// - `{added}`: Words added this session
+/// - `{deleted}`: Words deleted this session
+/// - `{pauses}`: Pauses longer than `--pause` seconds this session
+/// - `{streak}`: Consecutive days meeting `--goal`, including this session, empty if history is disabled
+/// - `{chapter}`: Current chapter and number of chapters, like `2/12`, empty outside projects
+/// - `{project_words}`: Words in all chapters, empty outside projects
+pub struct StatusFields<'a> {
+    pub words: usize,
+    pub chars: usize,
+    /// Words written since the start of the session
+    pub session_words: usize,
+    pub elapsed: Duration,
+    pub duration: Option<Duration>,
+    pub saved: bool,
+    pub mode: &'a WritingMode,
+    pub word_goal: Option<usize>,
+    pub stats: &'a SessionStats,
+    pub streak: Option<usize>,
+    /// Index of current chapter and number of chapters, if writing a project
+    pub chapter: Option<(usize, usize)>,
+    pub project_words: Option<usize>,
+}
+
+impl StatusFields<'_> {
+    /// Average words per minute over the session
+    pub fn wpm(&self) -> usize {
+        let minutes = self.elapsed.as_secs_f64() / 60.0;
+        if minutes < 1.0 / 60.0 {
+            return 0;
+        }
+        (self.session_words as f64 / minutes).round() as usize
+    }
+
+    /// Value of template field, None if field does not exist
+    fn field(&self, name: &str) -> Option<String> {
+        let value = match name {
+            "words" => self.words.to_string(),
+            "chars" => self.chars.to_string(),
+            "elapsed" => format_duration(self.elapsed),
+            "remaining" => self
+                .duration
+                .map(|duration| format_duration(duration.saturating_sub(self.elapsed)))
+                .unwrap_or_default(),
+            "saved" => String::from(if self.saved { "saved" } else { "unsaved" }),
+            "mode" => self.mode.to_string(),
+            "wpm" => self.wpm().to_string(),
+            "goal%" => self
+                .word_goal
+                .filter(|&goal| goal > 0)
+                .map(|goal| format!("{}%", self.session_words * 100 / goal))
+                .unwrap_or_default(),
+            "rwpm" => self.stats.rolling_wpm().to_string(),
+            "keystrokes" => self.stats.keystrokes.to_string(),
+            "added" => self.stats.words_added.to_string(),
+            "deleted" => self.stats.words_deleted.to_string(),
+            "pauses" => self.stats.pauses.to_string(),
+            "streak" => self
+                .streak
+                .map(|streak| streak.to_string())
+                .unwrap_or_default(),
+            "chapter" => self
+                .chapter
+                .map(|(index, count)| format!("{}/{}", index + 1, count))
+                .unwrap_or_default(),
+            "project_words" => self
+                .project_words
+                .map(|words| words.to_string())
+                .unwrap_or_default(),
+            _ => return None,
+        };
+        Some(value)
+    }
+}
+
+/// Replace `{field}` placeholders in template. Unknown fields are left as-is
+pub fn render_status(template: &str, fields: &StatusFields) -> String {
+    render_template(template, |name| fields.field(name))
+}
+
+/// Format duration as `m:ss`, or `h:mm:ss` past an hour
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (hours, minutes, seconds) = (secs / 3600, (secs / 60) % 60, secs % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_each_field() {
+        let mut stats = SessionStats::new(Duration::from_secs(5));
+        stats.keystrokes = 42;
+        stats.words_added = 12;
+        stats.words_deleted = 2;
+        stats.pauses = 3;
+        let mut fields = StatusFields {
+            words: 110,
+            chars: 640,
+            session_words: 10,
+            elapsed: Duration::from_secs(150),
+            duration: Some(Duration::from_secs(600)),
+            saved: false,
+            mode: &WritingMode::Hemingway,
+            word_goal: Some(40),
+            stats: &stats,
+            streak: Some(4),
+            chapter: Some((1, 12)),
+            project_words: Some(5000),
+        };
+        let cases = [
+            ("{words}", "110"),
+            ("{chars}", "640"),
+            ("{elapsed}", "2:30"),
+            ("{remaining}", "7:30"),
+            ("{saved}", "unsaved"),
+            ("{mode}", "hemingway"),
+            ("{wpm}", "4"),
+            ("{goal%}", "25%"),
+            ("{rwpm}", "0"),
+            ("{keystrokes}", "42"),
+            ("{added}", "12"),
+            ("{deleted}", "2"),
+            ("{pauses}", "3"),
+            ("{streak}", "4"),
+            ("{chapter}", "2/12"),
+            ("{project_words}", "5000"),
+        ];
+        for (template, expected) in cases {
+            assert_eq!(render_status(template, &fields), expected, "{}", template);
+        }
+
+        fields.saved = true;
+        fields.duration = None;
+        fields.word_goal = None;
+        fields.streak = None;
+        fields.chapter = None;
+        fields.project_words = None;
+        assert_eq!(
+            render_status(
+                "{saved}|{remaining}|{goal%}|{streak}|{chapter}|{project_words}",
+                &fields
+            ),
+            "saved|||||"
+        );
+    }
+
+    #[test]
+    fn keeps_unknown_fields() {
+        let stats = SessionStats::new(Duration::from_secs(5));
+        let fields = StatusFields {
+            words: 3,
+            chars: 13,
+            session_words: 3,
+            elapsed: Duration::ZERO,
+            duration: None,
+            saved: true,
+            mode: &WritingMode::Regular,
+            word_goal: None,
+            stats: &stats,
+            streak: None,
+            chapter: None,
+            project_words: None,
+        };
+        assert_eq!(
+            render_status("{words} words | {sentences} {unclosed", &fields),
+            "3 words | {sentences} {unclosed"
+        );
+    }
+
+    #[test]
+    fn formats_durations() {
+        assert_eq!(format_duration(Duration::ZERO), "0:00");
+        assert_eq!(format_duration(Duration::from_secs(59)), "0:59");
+        assert_eq!(format_duration(Duration::from_secs(61)), "1:01");
+        assert_eq!(format_duration(Duration::from_secs(3599)), "59:59");
+        assert_eq!(format_duration(Duration::from_secs(3600)), "1:00:00");
+        assert_eq!(format_duration(Duration::from_secs(36_005)), "10:00:05");
+    }
+}
@@ -0,0 +1,39 @@
+use chrono::{DateTime, Local};
+
+/// Replace `{field}` placeholders in template with the value returned by `field`.
+/// Fields for which `field` returns None are left as-is
+pub fn render_template(template: &str, field: impl Fn(&str) -> Option<String>) -> String {
+    let mut output = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        output.push_str(&rest[..start]);
+        let after_brace = &rest[start + 1..];
+        match after_brace.find('}') {
+            Some(end) => {
+                let name = &after_brace[..end];
+                match field(name) {
+                    Some(value) => output.push_str(&value),
+                    None => output.push_str(&rest[start..start + end + 2]),
+                }
+                rest = &after_brace[end + 1..];
+            }
+            None => {
+                output.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Value of a date field: `{date}` (2023-04-06), `{time}` (07:30) or `{datetime}` (2023-04-06 07:30)
+pub fn datetime_field(name: &str, now: &DateTime<Local>) -> Option<String> {
+    let format = match name {
+        "date" => "%Y-%m-%d",
+        "time" => "%H:%M",
+        "datetime" => "%Y-%m-%d %H:%M",
+        _ => return None,
+    };
+    Some(now.format(format).to_string())
+}
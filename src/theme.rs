@@ -0,0 +1,451 @@
+use std::collections::HashMap;
+use std::env;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tui::style::{Color, Modifier, Style};
+use tui::widgets::{Block, BorderType, Borders};
+
+/// Name of the theme used when none is given
+pub const DEFAULT_THEME: &str = "default";
+
+/// A theme as written in the user config file.
+///
+/// Every field is optional. Missing fields are taken from `base` if given, or left as the
+/// terminal default otherwise. Colors may be a name (`red`, `lightblue`, `reset`, ...),
+/// a 256-color index (`"236"`), or a hex code (`"#1c1c1c"`).
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ThemeSpec {
+    /// Name of a theme to inherit missing fields from
+    pub base: Option<String>,
+    /// Text color
+    pub fg: Option<String>,
+    /// Background color
+    pub bg: Option<String>,
+    /// Color of de-emphasized text
+    pub dim: Option<String>,
+    /// Background color of the line under the cursor
+    pub cursor_line: Option<String>,
+    /// Status line text color
+    pub status_fg: Option<String>,
+    /// Status line background color
+    pub status_bg: Option<String>,
+    /// One of `plain`, `rounded`, `double`, `thick`, or `none` to hide the border
+    pub border: Option<String>,
+}
+
+impl ThemeSpec {
+    /// Fill fields missing from self with the fields of other
+    fn inherit(mut self, other: ThemeSpec) -> ThemeSpec {
+        self.fg = self.fg.or(other.fg);
+        self.bg = self.bg.or(other.bg);
+        self.dim = self.dim.or(other.dim);
+        self.cursor_line = self.cursor_line.or(other.cursor_line);
+        self.status_fg = self.status_fg.or(other.status_fg);
+        self.status_bg = self.status_bg.or(other.status_bg);
+        self.border = self.border.or(other.border);
+        self.base = other.base;
+        self
+    }
+}
+
+/// Themes shipped with hemm
+fn builtin_spec(name: &str) -> Option<ThemeSpec> {
+    let spec = |fg: &str, bg: &str, dim: &str, cursor_line: &str, status: (&str, &str), border| {
+        ThemeSpec {
+            base: None,
+            fg: Some(fg.into()),
+            bg: Some(bg.into()),
+            dim: Some(dim.into()),
+            cursor_line: Some(cursor_line.into()),
+            status_fg: Some(status.0.into()),
+            status_bg: Some(status.1.into()),
+            border: Some(String::from(border)),
+        }
+    };
+    match name {
+        DEFAULT_THEME => Some(ThemeSpec::default()),
+        "dark" => Some(spec(
+            "#d0d0d0",
+            "#1c1c1c",
+            "#6c6c6c",
+            "#262626",
+            ("#a8a8a8", "#303030"),
+            "rounded",
+        )),
+        "light" => Some(spec(
+            "#303030",
+            "#fafafa",
+            "#a8a8a8",
+            "#eeeeee",
+            ("#4e4e4e", "#e4e4e4"),
+            "plain",
+        )),
+        "solarized" => Some(spec(
+            "#839496",
+            "#002b36",
+            "#586e75",
+            "#073642",
+            ("#93a1a1", "#073642"),
+            "plain",
+        )),
+        _ => None,
+    }
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ThemeError {
+    #[error("Unknown theme '{0}'")]
+    UnknownTheme(String),
+
+    #[error("Invalid color '{0}'")]
+    InvalidColor(String),
+
+    #[error("Invalid border '{0}'")]
+    InvalidBorder(String),
+}
+
+/// Colors the terminal is able to display
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorSupport {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+}
+
+/// The 16 ANSI colors and their usual RGB values, used to find the closest match
+const ANSI16: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (128, 0, 0)),
+    (Color::Green, (0, 128, 0)),
+    (Color::Yellow, (128, 128, 0)),
+    (Color::Blue, (0, 0, 128)),
+    (Color::Magenta, (128, 0, 128)),
+    (Color::Cyan, (0, 128, 128)),
+    (Color::Gray, (192, 192, 192)),
+    (Color::DarkGray, (128, 128, 128)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (0, 0, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+/// Channel values of the 6x6x6 color cube in the 256-color palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+impl ColorSupport {
+    /// Guess color support from the environment, the same way most terminal programs do
+    pub fn detect() -> Self {
+        let colorterm = env::var("COLORTERM").unwrap_or_default();
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            return ColorSupport::TrueColor;
+        }
+        let term = env::var("TERM").unwrap_or_default();
+        if term.contains("256color") {
+            ColorSupport::Ansi256
+        } else {
+            ColorSupport::Ansi16
+        }
+    }
+
+    /// Convert color to the closest one the terminal can display
+    pub fn adapt(&self, color: Color) -> Color {
+        match (self, color) {
+            (ColorSupport::TrueColor, _) => color,
+            (ColorSupport::Ansi256, Color::Rgb(r, g, b)) => Color::Indexed(nearest_256(r, g, b)),
+            (ColorSupport::Ansi256, _) => color,
+            (ColorSupport::Ansi16, Color::Rgb(r, g, b)) => nearest_16(r, g, b),
+            (ColorSupport::Ansi16, Color::Indexed(i)) => {
+                let (r, g, b) = indexed_to_rgb(i);
+                nearest_16(r, g, b)
+            }
+            (ColorSupport::Ansi16, _) => color,
+        }
+    }
+}
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+fn nearest_cube_level(value: u8) -> usize {
+    (0..CUBE_LEVELS.len())
+        .min_by_key(|&i| (CUBE_LEVELS[i] as i32 - value as i32).abs())
+        .unwrap()
+}
+
+fn nearest_256(r: u8, g: u8, b: u8) -> u8 {
+    let (ri, gi, bi) = (
+        nearest_cube_level(r),
+        nearest_cube_level(g),
+        nearest_cube_level(b),
+    );
+    let cube_index = 16 + 36 * ri + 6 * gi + bi;
+    let cube_rgb = (CUBE_LEVELS[ri], CUBE_LEVELS[gi], CUBE_LEVELS[bi]);
+
+    // The grayscale ramp is often closer for dark and neutral colors
+    let average = (r as u32 + g as u32 + b as u32) / 3;
+    let gray_step = (average.saturating_sub(8) / 10).min(23) as u8;
+    let gray_value = 8 + gray_step * 10;
+    let gray_index = 232 + gray_step;
+
+    if distance((gray_value, gray_value, gray_value), (r, g, b)) < distance(cube_rgb, (r, g, b)) {
+        gray_index
+    } else {
+        cube_index as u8
+    }
+}
+
+fn nearest_16(r: u8, g: u8, b: u8) -> Color {
+    ANSI16
+        .iter()
+        .min_by_key(|(_, rgb)| distance(*rgb, (r, g, b)))
+        .map(|(color, _)| *color)
+        .unwrap()
+}
+
+fn indexed_to_rgb(index: u8) -> (u8, u8, u8) {
+    match index {
+        0..=15 => ANSI16[index as usize].1,
+        16..=231 => {
+            let i = (index - 16) as usize;
+            (
+                CUBE_LEVELS[i / 36],
+                CUBE_LEVELS[(i / 6) % 6],
+                CUBE_LEVELS[i % 6],
+            )
+        }
+        _ => {
+            let value = 8 + (index - 232) * 10;
+            (value, value, value)
+        }
+    }
+}
+
+/// Parse color from its config file representation
+fn parse_color(value: &str) -> Result<Color, ThemeError> {
+    let invalid = || ThemeError::InvalidColor(value.to_string());
+    if let Some(hex) = value.strip_prefix('#') {
+        if hex.len() != 6 || !hex.is_ascii() {
+            return Err(invalid());
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid());
+        return Ok(Color::Rgb(channel(0)?, channel(2)?, channel(4)?));
+    }
+    if let Ok(index) = value.parse::<u8>() {
+        return Ok(Color::Indexed(index));
+    }
+    let color = match value.to_lowercase().replace(['-', '_'], "").as_str() {
+        "reset" | "default" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "darkgray" | "darkgrey" => Color::DarkGray,
+        "lightred" => Color::LightRed,
+        "lightgreen" => Color::LightGreen,
+        "lightyellow" => Color::LightYellow,
+        "lightblue" => Color::LightBlue,
+        "lightmagenta" => Color::LightMagenta,
+        "lightcyan" => Color::LightCyan,
+        "white" => Color::White,
+        _ => return Err(invalid()),
+    };
+    Ok(color)
+}
+
+fn parse_border(value: &str) -> Result<Option<BorderType>, ThemeError> {
+    match value.to_lowercase().as_str() {
+        "none" => Ok(None),
+        "plain" => Ok(Some(BorderType::Plain)),
+        "rounded" => Ok(Some(BorderType::Rounded)),
+        "double" => Ok(Some(BorderType::Double)),
+        "thick" => Ok(Some(BorderType::Thick)),
+        _ => Err(ThemeError::InvalidBorder(value.to_string())),
+    }
+}
+
+/// Resolved styles used when rendering the editor
+#[derive(Clone, Debug)]
+pub struct Theme {
+    /// Style of written text, and of the background of the editor
+    pub text: Style,
+    /// Style of de-emphasized text
+    pub dim: Style,
+    /// Style of the line under the cursor
+    pub cursor_line: Style,
+    /// Style of the status line
+    pub status_line: Style,
+    /// Border around the text, if any
+    pub border: Option<BorderType>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            text: Style::default(),
+            dim: Style::default().add_modifier(Modifier::DIM),
+            cursor_line: Style::default(),
+            status_line: Style::default(),
+            border: Some(BorderType::Plain),
+        }
+    }
+}
+
+impl Theme {
+    /// Look up theme by name in user themes, then in the built-in themes,
+    /// and adapt its colors to what the terminal supports
+    pub fn load(
+        name: &str,
+        user_themes: &HashMap<String, ThemeSpec>,
+        support: ColorSupport,
+    ) -> Result<Theme, ThemeError> {
+        let mut spec = ThemeSpec {
+            base: Some(name.to_string()),
+            ..ThemeSpec::default()
+        };
+        // Follow chain of bases, guarding against cycles
+        let mut visited = Vec::new();
+        while let Some(base) = spec.base.take() {
+            let base_spec = if visited.contains(&base) {
+                // A user theme may extend the built-in theme it shadows, e.g. `dark: {base: dark}`
+                match builtin_spec(&base) {
+                    Some(builtin) => builtin,
+                    None => break,
+                }
+            } else {
+                user_themes
+                    .get(&base)
+                    .cloned()
+                    .or_else(|| builtin_spec(&base))
+                    .ok_or_else(|| ThemeError::UnknownTheme(base.clone()))?
+            };
+            spec = spec.inherit(base_spec);
+            visited.push(base);
+        }
+        Theme::from_spec(&spec, support)
+    }
+
+    fn from_spec(spec: &ThemeSpec, support: ColorSupport) -> Result<Theme, ThemeError> {
+        let color = |value: &Option<String>| -> Result<Option<Color>, ThemeError> {
+            value
+                .as_deref()
+                .map(|value| parse_color(value).map(|color| support.adapt(color)))
+                .transpose()
+        };
+        let default = Theme::default();
+
+        let mut text = Style::default();
+        if let Some(fg) = color(&spec.fg)? {
+            text = text.fg(fg);
+        }
+        if let Some(bg) = color(&spec.bg)? {
+            text = text.bg(bg);
+        }
+        let dim = match color(&spec.dim)? {
+            Some(dim) => Style::default().fg(dim),
+            None => default.dim,
+        };
+        let cursor_line = match color(&spec.cursor_line)? {
+            Some(cursor_line) => Style::default().bg(cursor_line),
+            None => default.cursor_line,
+        };
+        let mut status_line = text;
+        if let Some(status_fg) = color(&spec.status_fg)? {
+            status_line = status_line.fg(status_fg);
+        }
+        if let Some(status_bg) = color(&spec.status_bg)? {
+            status_line = status_line.bg(status_bg);
+        }
+        let border = match &spec.border {
+            Some(border) => parse_border(border)?,
+            None => default.border,
+        };
+
+        Ok(Theme {
+            text,
+            dim,
+            cursor_line,
+            status_line,
+            border,
+        })
+    }
+
+    /// Block surrounding the text area
+    pub fn block(&self) -> Block<'static> {
+        match self.border {
+            Some(border_type) => Block::default()
+                .borders(Borders::ALL)
+                .border_type(border_type)
+                .border_style(self.text),
+            None => Block::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_colors() {
+        assert_eq!(parse_color("#1c1c1c"), Ok(Color::Rgb(0x1c, 0x1c, 0x1c)));
+        assert_eq!(parse_color("236"), Ok(Color::Indexed(236)));
+        assert_eq!(parse_color("Light-Blue"), Ok(Color::LightBlue));
+        assert_eq!(parse_color("reset"), Ok(Color::Reset));
+        for invalid in ["#12345", "#gggggg", "256", "purple"] {
+            assert_eq!(
+                parse_color(invalid),
+                Err(ThemeError::InvalidColor(invalid.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn adapts_colors_to_terminal() {
+        let dark_gray = Color::Rgb(0x1c, 0x1c, 0x1c);
+        assert_eq!(ColorSupport::TrueColor.adapt(dark_gray), dark_gray);
+        assert_eq!(ColorSupport::Ansi256.adapt(dark_gray), Color::Indexed(234));
+        assert_eq!(
+            ColorSupport::Ansi256.adapt(Color::Rgb(255, 0, 0)),
+            Color::Indexed(196)
+        );
+        assert_eq!(ColorSupport::Ansi16.adapt(dark_gray), Color::Black);
+        assert_eq!(
+            ColorSupport::Ansi16.adapt(Color::Indexed(196)),
+            Color::LightRed
+        );
+        assert_eq!(ColorSupport::Ansi16.adapt(Color::Blue), Color::Blue);
+    }
+
+    #[test]
+    fn resolves_bases() {
+        let themes: HashMap<String, ThemeSpec> = serde_yaml::from_str(
+            "dark: {base: dark, fg: red}\nnight: {base: dark, border: none}\nloop: {base: loop2}\nloop2: {base: loop}\n",
+        )
+        .unwrap();
+
+        // Shadowing a built-in theme extends it
+        let theme = Theme::load("dark", &themes, ColorSupport::TrueColor).unwrap();
+        assert_eq!(theme.text.fg, Some(Color::Red));
+        assert_eq!(theme.text.bg, Some(Color::Rgb(0x1c, 0x1c, 0x1c)));
+
+        let theme = Theme::load("night", &themes, ColorSupport::TrueColor).unwrap();
+        assert_eq!(theme.text.fg, Some(Color::Red));
+        assert_eq!(theme.border, None);
+
+        assert!(Theme::load("loop", &themes, ColorSupport::TrueColor).is_ok());
+        assert_eq!(
+            Theme::load("missing", &themes, ColorSupport::TrueColor).unwrap_err(),
+            ThemeError::UnknownTheme(String::from("missing"))
+        );
+    }
+}
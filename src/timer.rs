@@ -1,30 +1,24 @@
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, Condvar, Mutex};
 use std::thread::{self, JoinHandle};
 use std::time::{Duration, Instant};
 
 use crate::config::Config;
+use crate::event::{AppEvent, EventSender, Shutdown};
 
+/// Starts thread that sends the elapsed time of the session once per second
 pub fn start_timer_thread(
-    elapsed_time_handle: Arc<Mutex<Duration>>,
-    running_handle: Arc<AtomicBool>,
-    condvar: Arc<Condvar>,
-    condmut: Arc<Mutex<()>>,
+    events: EventSender,
+    shutdown: Shutdown,
     _config: &Config,
 ) -> JoinHandle<()> {
     thread::spawn(move || {
         let start_time = Instant::now();
-        let timer_interval = Duration::from_millis(100);
-        while running_handle.load(Ordering::SeqCst) {
-            let current_elapsed = Instant::now().duration_since(start_time);
-            {
-                *elapsed_time_handle.lock().unwrap() = current_elapsed;
+        loop {
+            // Wake up on the next whole second, when the displayed time changes
+            let next_second = Duration::from_secs(start_time.elapsed().as_secs() + 1);
+            if shutdown.wait(next_second.saturating_sub(start_time.elapsed())) {
+                break;
             }
-
-            // Sleep for a short duration before updating the elapsed time again
-            // Condvar allows instant wakeup on signal
-            let guard = condmut.lock().unwrap();
-            let _ = condvar.wait_timeout(guard, timer_interval).unwrap();
+            _ = events.send(AppEvent::Tick(start_time.elapsed()));
         }
     })
 }
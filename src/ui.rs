@@ -0,0 +1,120 @@
+use tui::backend::Backend;
+use tui::layout::{Constraint, Direction, Layout};
+use tui::text::{Span, Spans};
+use tui::widgets::{Block, Paragraph, Wrap};
+use tui::Frame;
+
+use crate::config::WritingMode;
+use crate::session::Session;
+
+/// Render the editor: the text with margins around it, and the status line below
+pub fn draw<B: Backend>(f: &mut Frame<B>, session: &Session) {
+    let config = session.config();
+    // Computed before locking the buffer, which it locks itself
+    let status = if config.show_status_line {
+        session.status()
+    } else {
+        String::new()
+    };
+
+    let status_line_height = if config.show_status_line { 1 } else { 0 };
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Min(1),                     // TextArea
+                Constraint::Length(status_line_height), // Status line
+            ]
+            .as_ref(),
+        );
+
+    // Fill background with theme colors
+    f.render_widget(Block::default().style(config.theme.text), f.size());
+    let chunks = layout.split(f.size());
+    let textarea_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(2), // Margin
+                Constraint::Min(1),    // Main area
+                // One less row margin on bottom if there is a status line
+                Constraint::Length(2 - status_line_height),
+            ]
+            .as_ref(),
+        )
+        .horizontal_margin(4);
+    let mut textarea_chunk = textarea_layout.split(chunks[0])[1];
+
+    // Prompt of the session above the text, followed by a blank line
+    if let Some(prompt) = session.prompt() {
+        let width = usize::from(textarea_chunk.width.max(1));
+        let rows: usize = prompt
+            .lines()
+            .map(|line| line.chars().count().max(1).div_ceil(width))
+            .sum();
+        let prompt_height = (rows as u16).min(textarea_chunk.height / 3);
+        let prompt_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(prompt_height),
+                Constraint::Length(1),
+                Constraint::Min(1),
+            ])
+            .split(textarea_chunk);
+        let prompt_widget = Paragraph::new(prompt)
+            .style(config.theme.dim)
+            .wrap(Wrap { trim: true });
+        f.render_widget(prompt_widget, prompt_chunks[0]);
+        textarea_chunk = prompt_chunks[2];
+    }
+
+    let buffer = session.buffer();
+    let mut buffer = buffer.lock().unwrap();
+    // In append-only mode, show end of existing contents above the new text.
+    // Blind mode hides everything written before, so it is not shown there
+    let existing_tail = match config.writing_mode {
+        WritingMode::Blind => Vec::new(),
+        _ => buffer.existing_tail((textarea_chunk.height / 3).into()),
+    };
+    if !existing_tail.is_empty() {
+        let existing_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(existing_tail.len() as u16),
+                Constraint::Min(1),
+            ])
+            .split(textarea_chunk);
+        let existing_text: Vec<Spans> = existing_tail
+            .iter()
+            .map(|line| Spans::from(line.to_string()))
+            .collect();
+        let existing_widget = Paragraph::new(existing_text).style(config.theme.dim);
+        f.render_widget(existing_widget, existing_chunks[0]);
+        textarea_chunk = existing_chunks[1];
+    }
+    if config.writing_mode == WritingMode::Blind {
+        let blind_widget = buffer.blind_widget(config.blind_visible_words);
+        f.render_widget(blind_widget, textarea_chunk);
+    } else {
+        let buffer_widget = buffer.textarea.widget();
+        f.render_widget(buffer_widget, textarea_chunk);
+    }
+
+    if !config.show_status_line {
+        return;
+    }
+    let status_line_layout = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Min(1),
+            Constraint::Length(status.chars().count() as u16 + 1),
+        ]);
+    let status_chunks = status_line_layout.split(chunks[1]);
+    let message = match buffer.get_message() {
+        Some(message) => message,
+        None => "",
+    };
+    f.render_widget(Block::default().style(config.theme.status_line), chunks[1]);
+    f.render_widget(Paragraph::new(Span::raw(message)), status_chunks[0]);
+    f.render_widget(Paragraph::new(Span::raw(status)), status_chunks[1]);
+}
@@ -0,0 +1,182 @@
+//! Hard-wrapping of paragraphs, for saving files at a fixed width and for plain text exports
+
+/// Wrap paragraph at width columns, breaking lines at the last space between words.
+/// Other whitespace is kept as it is, and words longer than width get a line of their own
+pub fn wrap_paragraph(paragraph: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut has_words = false;
+    for (space, word) in words(paragraph) {
+        let len = line.chars().count() + space.chars().count() + word.chars().count();
+        match space.strip_suffix(' ') {
+            // The space at the break is put back by unwrap_text
+            Some(kept) if has_words && !word.is_empty() && len > width => {
+                lines.push(std::mem::take(&mut line) + kept);
+            }
+            _ => line += space,
+        }
+        line += word;
+        has_words |= !word.is_empty();
+    }
+    if has_words {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Words of text, each with the whitespace before it.
+/// Whitespace at the end of text comes with an empty word
+fn words(text: &str) -> Vec<(&str, &str)> {
+    let mut words = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let start = rest.len() - rest.trim_start().len();
+        let end = rest[start..]
+            .find(char::is_whitespace)
+            .map_or(rest.len(), |len| start + len);
+        words.push((&rest[..start], &rest[start..end]));
+        rest = &rest[end..];
+    }
+    words
+}
+
+/// Wrap each line at width columns. Lines are paragraphs, separated by blank lines
+pub fn wrap_lines(lines: &[String], width: usize) -> Vec<String> {
+    lines
+        .iter()
+        .flat_map(|line| {
+            if line.trim().is_empty() {
+                vec![line.clone()]
+            } else {
+                wrap_paragraph(line, width)
+            }
+        })
+        .collect()
+}
+
+/// Undo `wrap_lines` at width: join the lines of each paragraph into one line. A line is only
+/// joined to the one before when that line could have been wrapped at width, its own first word
+/// wouldn't have fit there, and it doesn't start a list item or heading. Other line breaks,
+/// e.g. in a poem or a list, are kept
+pub fn unwrap_text(text: &str, width: usize) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut previous: Option<&str> = None;
+    for line in text.lines() {
+        let first_word = line.split(char::is_whitespace).next().unwrap_or_default();
+        // Wrapped lines start with a word
+        let is_wrapped = !first_word.is_empty()
+            && !starts_block(first_word)
+            && previous.is_some_and(|previous| {
+                is_wrapped_line(previous, width)
+                    && previous.chars().count() + 1 + first_word.chars().count() > width
+            });
+        match lines.last_mut() {
+            Some(last) if is_wrapped => {
+                last.push(' ');
+                last.push_str(line);
+            }
+            _ => lines.push(line.to_string()),
+        }
+        previous = Some(line);
+    }
+    let mut unwrapped = lines.join("\n");
+    if text.ends_with('\n') {
+        unwrapped.push('\n');
+    }
+    unwrapped
+}
+
+/// Whether line could be a line made by `wrap_paragraph` at width: no longer than width, not
+/// counting the spaces kept at the break, unless it is a single word too long to be broken
+fn is_wrapped_line(line: &str, width: usize) -> bool {
+    let words = line.trim();
+    !words.is_empty()
+        && (line.trim_end().chars().count() <= width || !words.contains(char::is_whitespace))
+}
+
+/// Whether a line starting with word starts a Markdown list item, quote or heading
+fn starts_block(word: &str) -> bool {
+    matches!(word, "-" | "*" | "+" | ">")
+        || word.starts_with('#')
+        || word
+            .strip_suffix(['.', ')'])
+            .is_some_and(|number| !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrapped_lines_unwrap_to_paragraphs() {
+        let lines = vec![
+            String::from("  The rain fell on the roofs of the town all night."),
+            String::new(),
+            String::from("Streets were rivers."),
+        ];
+        let wrapped = wrap_lines(&lines, 20);
+        assert_eq!(
+            wrapped,
+            [
+                "  The rain fell on",
+                "the roofs of the",
+                "town all night.",
+                "",
+                "Streets were rivers."
+            ]
+        );
+        assert_eq!(
+            unwrap_text(&(wrapped.join("\n") + "\n"), 20),
+            lines.join("\n") + "\n"
+        );
+    }
+
+    #[test]
+    fn keeps_whitespace_and_short_lines() {
+        let lines = vec![
+            String::from("Roses  are red,"),
+            String::from("sky\tis blue."),
+            String::from("Sugar is sweet,  and so  are you, my dear."),
+        ];
+        let wrapped = wrap_lines(&lines, 20);
+        assert_eq!(
+            wrapped,
+            [
+                "Roses  are red,",
+                "sky\tis blue.",
+                "Sugar is sweet,  and",
+                "so  are you, my",
+                "dear."
+            ]
+        );
+        assert_eq!(unwrap_text(&wrapped.join("\n"), 20), lines.join("\n"));
+        // Breaking at the last of several spaces keeps the others at the end of the line
+        assert_eq!(
+            wrap_paragraph("Sugar is sweet,   and", 16),
+            ["Sugar is sweet,  ", "and"]
+        );
+        assert_eq!(
+            unwrap_text("Sugar is sweet,  \nand", 16),
+            "Sugar is sweet,   and"
+        );
+    }
+
+    #[test]
+    fn keeps_long_lines_lists_and_headings() {
+        // Lines longer than width weren't wrapped by hemm, e.g. verse written without --wrap
+        let verse = "Whose woods these are I think I know.\nHis house is in the village though;";
+        assert_eq!(unwrap_text(verse, 20), verse);
+
+        let list = "Things to pack:\n- a coat\n* boots\n+ a map\n1. the tickets\n2) keys";
+        assert_eq!(unwrap_text(list, 15), list);
+
+        let headings = "Rain fell all day\n# Chapter one\n## The letter\n> Dear sir";
+        assert_eq!(unwrap_text(headings, 17), headings);
+
+        // A single word too long for width is on its own line, and can be followed by a wrapped line
+        assert_eq!(
+            unwrap_text("Supercalifragilistic\nwords", 10),
+            "Supercalifragilistic words"
+        );
+    }
+}
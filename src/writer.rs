@@ -0,0 +1,154 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::crypto::Cipher;
+use crate::file_format::{Encoding, FileFormat, UnencodableChar};
+
+/// Start of the file that is never rewritten, only the text after it
+pub struct Head {
+    /// Length in bytes on disk
+    pub len: u64,
+    /// Contents in append-only mode. Not kept in memory for large files,
+    /// where it is copied from the file when writing elsewhere
+    pub text: Option<String>,
+}
+
+/// Writes text of a buffer to disk, in the format of the file
+pub struct FileWriter {
+    /// Path to file output
+    pub path: PathBuf,
+    /// Path to backup file
+    pub back_path: PathBuf,
+    /// Line endings and encoding of the file, kept when saving
+    pub format: FileFormat,
+    /// Set when the file is encrypted. Everything written to disk is encrypted with it
+    pub cipher: Option<Cipher>,
+    /// Set when only the text after the start of the file is written
+    pub head: Option<Head>,
+    /// Newest revision of the buffer written to path. Held while writing, so saves of the
+    /// autosave thread and a control script never interleave
+    pub written: Mutex<Option<u64>>,
+}
+
+impl FileWriter {
+    /// Write text to path. `text` is everything after the head, joined with the line endings of the
+    /// file. The head is left untouched in the output file, and copied when writing elsewhere
+    fn write(&self, path: &Path, text: &str) -> io::Result<()> {
+        match self.write_as(path, text, &self.format) {
+            // Backups and snapshots are written in UTF-8 instead, so the text isn't lost
+            Err(err) if path != self.path && UnencodableChar::caused(&err) => {
+                let utf8 = FileFormat {
+                    encoding: Encoding::Utf8,
+                    ..self.format
+                };
+                self.write_as(path, text, &utf8)
+            }
+            result => result,
+        }
+    }
+
+    fn write_as(&self, path: &Path, text: &str, format: &FileFormat) -> io::Result<()> {
+        // Encrypted contents can't be patched, so the whole file is rewritten
+        if let Some(cipher) = &self.cipher {
+            let head = self.head.as_ref().and_then(|head| head.text.as_deref());
+            let contents = head.unwrap_or_default().to_string() + text;
+            return fs::write(path, cipher.encrypt(&format.encode(&contents, true)?)?);
+        }
+        // Encoded before opening the file, so a failure leaves it untouched
+        let data = format.encode(text, self.head.is_none())?;
+        match &self.head {
+            Some(head) if path == self.path => {
+                let mut f = OpenOptions::new().write(true).open(path)?;
+                f.set_len(head.len)?;
+                f.seek(SeekFrom::End(0))?;
+                f.write_all(&data)
+            }
+            Some(head) => {
+                let head_data = match &head.text {
+                    Some(head_text) => format.encode(head_text, true)?,
+                    None => {
+                        let mut head_data = Vec::new();
+                        File::open(&self.path)?
+                            .take(head.len)
+                            .read_to_end(&mut head_data)?;
+                        if format.encoding != self.format.encoding {
+                            // Only Latin-1 files are written in another encoding, and each of
+                            // their bytes is one character
+                            let head_text: String =
+                                head_data.iter().map(|&byte| byte as char).collect();
+                            head_data = format.encode(&head_text, true)?;
+                        }
+                        head_data
+                    }
+                };
+                let mut f = File::create(path)?;
+                f.write_all(&head_data)?;
+                f.write_all(&data)
+            }
+            None => fs::write(path, data),
+        }
+    }
+}
+
+/// Text of a buffer copied out of it, so it can be written without holding the buffer lock
+pub struct PendingSave {
+    writer: Arc<FileWriter>,
+    text: String,
+    /// Revision of the buffer the text was copied at
+    revision: u64,
+}
+
+impl PendingSave {
+    pub fn new(writer: Arc<FileWriter>, text: String, revision: u64) -> Self {
+        Self {
+            writer,
+            text,
+            revision,
+        }
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// File the text is saved to
+    pub fn path(&self) -> &Path {
+        &self.writer.path
+    }
+
+    /// File the text is saved to when saving to path fails
+    pub fn backup_path(&self) -> &Path {
+        &self.writer.back_path
+    }
+
+    /// Whether the text was copied from the buffer that writer belongs to
+    pub fn is_from(&self, writer: &Arc<FileWriter>) -> bool {
+        Arc::ptr_eq(&self.writer, writer)
+    }
+
+    /// Save to final filepath, unless a newer revision was saved while this one waited
+    pub fn write(&self) -> io::Result<()> {
+        let mut written = self.writer.written.lock().unwrap();
+        if written.is_some_and(|revision| revision >= self.revision) {
+            return Ok(());
+        }
+        self.writer.write(&self.writer.path, &self.text)?;
+        *written = Some(self.revision);
+        Ok(())
+    }
+
+    /// Save to backup filepath
+    pub fn write_backup(&self) -> io::Result<()> {
+        let _written = self.writer.written.lock().unwrap();
+        self.writer.write(&self.writer.back_path, &self.text)
+    }
+
+    /// Save a copy to path, e.g. a snapshot. Encrypted files stay encrypted
+    pub fn write_copy(&self, path: &Path) -> io::Result<()> {
+        // Large files are copied from the file being saved
+        let _written = self.writer.written.lock().unwrap();
+        self.writer.write(path, &self.text)
+    }
+}
@@ -0,0 +1,109 @@
+//! Minimal writer of ZIP archives, as used by EPUB and DOCX files.
+//! Files are stored without compression, which every reader supports
+
+/// CRC-32 checksum of data, as used by ZIP
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+/// Entry already written to the archive, listed again in the central directory
+struct Entry {
+    name: String,
+    crc: u32,
+    size: u32,
+    offset: u32,
+}
+
+/// Builds a ZIP archive in memory. Files appear in the archive in the order they are added
+#[derive(Default)]
+pub struct ZipWriter {
+    data: Vec<u8>,
+    entries: Vec<Entry>,
+}
+
+impl ZipWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add file with contents to the archive
+    pub fn add(&mut self, name: &str, contents: &[u8]) {
+        let entry = Entry {
+            name: String::from(name),
+            crc: crc32(contents),
+            size: contents.len() as u32,
+            offset: self.data.len() as u32,
+        };
+        // Local file header
+        self.data.extend(0x0403_4b50u32.to_le_bytes());
+        self.entry_fields(&entry);
+        self.data.extend(name.as_bytes());
+        self.data.extend(contents);
+        self.entries.push(entry);
+    }
+
+    /// Fields shared by the local file header and the central directory, from the version
+    /// needed to extract up to the extra field length
+    fn entry_fields(&mut self, entry: &Entry) {
+        self.data.extend(10u16.to_le_bytes()); // Version needed to extract: 1.0
+        self.data.extend(0x0800u16.to_le_bytes()); // Flags: name is UTF-8
+        self.data.extend(0u16.to_le_bytes()); // Compression: stored
+        self.data.extend(0u16.to_le_bytes()); // Modification time
+        self.data.extend(0x0021u16.to_le_bytes()); // Modification date: 1980-01-01
+        self.data.extend(entry.crc.to_le_bytes());
+        self.data.extend(entry.size.to_le_bytes()); // Compressed size
+        self.data.extend(entry.size.to_le_bytes()); // Uncompressed size
+        self.data.extend((entry.name.len() as u16).to_le_bytes());
+        self.data.extend(0u16.to_le_bytes()); // Extra field length
+    }
+
+    /// Write the central directory, and return the archive
+    pub fn finish(mut self) -> Vec<u8> {
+        let directory_offset = self.data.len() as u32;
+        let entries = std::mem::take(&mut self.entries);
+        for entry in &entries {
+            self.data.extend(0x0201_4b50u32.to_le_bytes());
+            self.data.extend(10u16.to_le_bytes()); // Version made by
+            self.entry_fields(entry);
+            self.data.extend(0u16.to_le_bytes()); // Comment length
+            self.data.extend(0u16.to_le_bytes()); // Disk number
+            self.data.extend(0u16.to_le_bytes()); // Internal attributes
+            self.data.extend(0u32.to_le_bytes()); // External attributes
+            self.data.extend(entry.offset.to_le_bytes());
+            self.data.extend(entry.name.as_bytes());
+        }
+        let directory_size = self.data.len() as u32 - directory_offset;
+        // End of central directory
+        self.data.extend(0x0605_4b50u32.to_le_bytes());
+        self.data.extend(0u16.to_le_bytes()); // Disk number
+        self.data.extend(0u16.to_le_bytes()); // Disk with the central directory
+        self.data.extend((entries.len() as u16).to_le_bytes());
+        self.data.extend((entries.len() as u16).to_le_bytes());
+        self.data.extend(directory_size.to_le_bytes());
+        self.data.extend(directory_offset.to_le_bytes());
+        self.data.extend(0u16.to_le_bytes()); // Comment length
+        self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums_match_zip() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(
+            crc32(b"The quick brown fox jumps over the lazy dog"),
+            0x414F_A339
+        );
+    }
+}
@@ -0,0 +1,12 @@
+//! Helpers shared by the integration tests
+use std::path::Path;
+
+use clap::Parser;
+use hemm::cli::Cli;
+use hemm::config::Config;
+
+/// Config for writing to path, with command line args
+pub fn config(path: &Path, args: &[&str]) -> Config {
+    let path = path.to_str().unwrap();
+    Config::new(&Cli::parse_from(["hemm", path].iter().chain(args))).unwrap()
+}
@@ -0,0 +1,248 @@
+use std::fs;
+use std::thread;
+use std::time::Duration;
+
+use clap::Parser;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use hemm::autosave::start_autosave_thread;
+use hemm::cli::Cli;
+use hemm::config::Config;
+use hemm::event::{event_bus, shutdown_channel};
+use hemm::project::Project;
+use hemm::prompt::PromptPlacement;
+use hemm::session::Session;
+
+mod common;
+use common::config;
+
+fn press(session: &mut Session, code: KeyCode) {
+    session.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+}
+
+#[test]
+fn hemingway_mode_ignores_deletion_and_navigation() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("pages.txt");
+    let config = config(&path, &["--hemingway", "true"]);
+    let mut session = Session::new(&config).unwrap();
+
+    session.type_text("The quick brwon");
+    for code in [
+        KeyCode::Backspace,
+        KeyCode::Delete,
+        KeyCode::Left,
+        KeyCode::Up,
+    ] {
+        press(&mut session, code);
+    }
+    session.type_text(" -- brown fox\njumps");
+    assert_eq!(session.text(), "The quick brwon -- brown fox\njumps");
+
+    session.save().unwrap();
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "The quick brwon -- brown fox\njumps\n"
+    );
+}
+
+#[test]
+fn regular_mode_allows_deletion() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("notes.txt");
+    let config = config(&path, &[]);
+    let mut session = Session::new(&config).unwrap();
+
+    session.type_text("helo");
+    press(&mut session, KeyCode::Backspace);
+    session.type_text("lo");
+    session.save().unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "hello\n");
+}
+
+#[test]
+fn resumes_existing_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("journal.txt");
+    fs::write(&path, "Monday\n").unwrap();
+    let config = config(&path, &["--hemingway", "true"]);
+    let mut session = Session::new(&config).unwrap();
+
+    session.type_text(": rain");
+    session.save().unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "Monday: rain\n");
+}
+
+#[test]
+fn status_reflects_typing_and_saving() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("status.txt");
+    let config = config(&path, &["--status", "{words} words, {saved}"]);
+    let mut session = Session::new(&config).unwrap();
+
+    session.type_text("one two three");
+    assert_eq!(session.status(), "3 words, unsaved");
+    session.save().unwrap();
+    assert_eq!(session.status(), "3 words, saved");
+}
+
+#[test]
+fn escape_finishes_session() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = config(&dir.path().join("done.txt"), &[]);
+    let mut session = Session::new(&config).unwrap();
+
+    session.type_text("last words");
+    assert!(!session.is_finished());
+    press(&mut session, KeyCode::Esc);
+    assert!(session.is_finished());
+}
+
+#[test]
+fn prompt_header_is_not_counted_as_written() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("pages.txt");
+    let config = config(&path, &[]);
+    let mut session = Session::new(&config).unwrap();
+
+    session.set_prompt("What woke you up?", PromptPlacement::Header);
+    session.type_text("Birds");
+    assert_eq!(session.text(), "What woke you up?\n\nBirds");
+    assert_eq!(session.buffer().lock().unwrap().session_word_count(), 1);
+}
+
+#[test]
+fn project_chapters_can_be_switched() {
+    let dir = tempfile::tempdir().unwrap();
+    let manifest = dir.path().join("book.yaml");
+    fs::write(&manifest, "chapters: [one.txt, two.txt]\n").unwrap();
+    fs::write(dir.path().join("two.txt"), "Already written\n").unwrap();
+    let project = Project::load(&manifest).unwrap();
+    let config = config(
+        &project.chapter_path(0),
+        &["--status", "{chapter} {words}/{project_words}"],
+    );
+    let mut session = Session::new(&config).unwrap();
+    session.set_project(project).unwrap();
+
+    session.type_text("Opening line");
+    assert_eq!(session.status(), "1/2 2/4");
+    let next = KeyEvent::new(KeyCode::PageDown, KeyModifiers::CONTROL);
+    session.handle_key(next);
+    assert_eq!(session.status(), "2/2 2/4");
+    assert_eq!(session.text(), "Already written");
+    assert_eq!(session.buffer().lock().unwrap().session_word_count(), 2);
+    // The chapter that was left is saved
+    assert_eq!(
+        fs::read_to_string(dir.path().join("one.txt")).unwrap(),
+        "Opening line\n"
+    );
+
+    // There is no chapter after the last one
+    session.handle_key(next);
+    assert_eq!(session.project().unwrap().current(), 1);
+}
+
+#[test]
+fn autosave_during_chapter_switch_keeps_newest_text() {
+    let dir = tempfile::tempdir().unwrap();
+    let manifest = dir.path().join("book.yaml");
+    fs::write(&manifest, "chapters: [one.txt, two.txt]\n").unwrap();
+    let project = Project::load(&manifest).unwrap();
+    let config = config(&project.chapter_path(0), &[]);
+    let mut session = Session::new(&config).unwrap();
+    session.set_project(project).unwrap();
+
+    session.type_text("Opening");
+    // Copied by the autosave thread, which writes it only after the switch
+    let autosave = session.buffer().lock().unwrap().pending_save();
+    session.type_text(" line");
+    let next = KeyEvent::new(KeyCode::PageDown, KeyModifiers::CONTROL);
+    session.handle_key(next);
+    assert_eq!(session.project().unwrap().current(), 1);
+    autosave.write().unwrap();
+    session.buffer().lock().unwrap().mark_saved(&autosave);
+
+    assert_eq!(
+        fs::read_to_string(dir.path().join("one.txt")).unwrap(),
+        "Opening line\n"
+    );
+    // The chapter now open is untouched
+    assert!(!dir.path().join("two.txt").exists());
+}
+
+#[test]
+fn chapters_keep_snapshots_of_their_own() {
+    let dir = tempfile::tempdir().unwrap();
+    let manifest = dir.path().join("book.yaml");
+    fs::write(&manifest, "chapters: [one.txt, two.txt]\n").unwrap();
+    let project = Project::load(&manifest).unwrap();
+    let snapshot_dir = dir.path().join("snapshots");
+    let config = config(
+        &project.chapter_path(0),
+        &[
+            "--snapshots",
+            "true",
+            "--snapshot-dir",
+            snapshot_dir.to_str().unwrap(),
+            "--interval",
+            "1",
+        ],
+    );
+    let mut session = Session::new(&config).unwrap();
+    session.set_project(project).unwrap();
+    let (events, _event_receiver) = event_bus();
+    let (signal, shutdown) = shutdown_channel();
+    let autosave = start_autosave_thread(session.buffer(), events, shutdown, &config);
+
+    // Each chapter is autosaved once
+    session.type_text("Chapter one");
+    thread::sleep(Duration::from_millis(1500));
+    session.handle_key(KeyEvent::new(KeyCode::PageDown, KeyModifiers::CONTROL));
+    session.type_text("Chapter two");
+    thread::sleep(Duration::from_millis(1500));
+    drop(signal);
+    autosave.join().unwrap();
+
+    for (chapter, text) in [("one.txt", "Chapter one\n"), ("two.txt", "Chapter two\n")] {
+        let snapshots = config.snapshots.as_ref().unwrap();
+        let snapshots = snapshots.of(&dir.path().join(chapter)).unwrap();
+        let latest = snapshots.find(None).unwrap().unwrap();
+        assert_eq!(fs::read_to_string(latest).unwrap(), text, "{}", chapter);
+    }
+}
+
+#[test]
+fn encrypted_sessions_cannot_be_recorded() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("secret.txt");
+    let record = dir.path().join("keys.jsonl");
+    let args = [
+        "hemm",
+        path.to_str().unwrap(),
+        "--encrypt",
+        "true",
+        "--record",
+        record.to_str().unwrap(),
+    ];
+    let error = Config::new(&Cli::parse_from(args)).unwrap_err();
+    assert_eq!(error.path, record);
+    assert!(error.error_type.to_string().contains("--record"));
+}
+
+#[test]
+fn encrypted_sessions_cannot_be_broadcast() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("secret.txt");
+    let socket = dir.path().join("hemm.sock");
+    let args = [
+        "hemm",
+        path.to_str().unwrap(),
+        "--encrypt",
+        "true",
+        "--broadcast",
+        socket.to_str().unwrap(),
+    ];
+    let error = Config::new(&Cli::parse_from(args)).unwrap_err();
+    assert_eq!(error.path, socket);
+    assert!(error.error_type.to_string().contains("--broadcast"));
+}
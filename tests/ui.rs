@@ -0,0 +1,148 @@
+//! Golden snapshot tests of the editor UI, rendered with tui's TestBackend.
+//! Snapshots are stored in `tests/snapshots`, with each row of the terminal between `|`s.
+//! Run with `UPDATE_SNAPSHOTS=1` to write new snapshots after changing the UI, and review the diff
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use hemm::prompt::PromptPlacement;
+use hemm::session::Session;
+use hemm::ui::draw;
+use tui::backend::TestBackend;
+use tui::Terminal;
+
+mod common;
+use common::config;
+
+/// Draw session, and return the terminal contents as text
+fn render(terminal: &mut Terminal<TestBackend>, session: &Session) -> String {
+    terminal.draw(|f| draw(f, session)).unwrap();
+    let buffer = terminal.backend().buffer();
+    let mut text = String::new();
+    for y in 0..buffer.area.height {
+        text.push('|');
+        for x in 0..buffer.area.width {
+            text += &buffer.get(x, y).symbol;
+        }
+        text += "|\n";
+    }
+    text
+}
+
+fn assert_snapshot(name: &str, actual: &str) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/snapshots")
+        .join(format!("{}.txt", name));
+    if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|_| panic!("No snapshot {:?}, run with UPDATE_SNAPSHOTS=1", path));
+    assert_eq!(actual, expected, "UI differs from snapshot {:?}", path);
+}
+
+#[test]
+fn margins_around_text() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = config(&dir.path().join("ui.txt"), &[]);
+    let mut session = Session::new(&config).unwrap();
+    session.type_text("Hello\nworld");
+
+    let mut terminal = Terminal::new(TestBackend::new(24, 8)).unwrap();
+    assert_snapshot("margins", &render(&mut terminal, &session));
+}
+
+#[test]
+fn status_line_fields() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = config(
+        &dir.path().join("ui.txt"),
+        &["--status", "{words} words | {saved}"],
+    );
+    let mut session = Session::new(&config).unwrap();
+    session.type_text("one two three");
+
+    let mut terminal = Terminal::new(TestBackend::new(32, 8)).unwrap();
+    assert_snapshot("status_line", &render(&mut terminal, &session));
+}
+
+#[test]
+fn hidden_status_line() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = config(
+        &dir.path().join("ui.txt"),
+        &["--status-line", "false", "--status", "{words} words"],
+    );
+    let mut session = Session::new(&config).unwrap();
+    session.type_text("one two three");
+
+    let mut terminal = Terminal::new(TestBackend::new(24, 8)).unwrap();
+    assert_snapshot("hidden_status_line", &render(&mut terminal, &session));
+}
+
+#[test]
+fn message_in_status_line() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = config(&dir.path().join("ui.txt"), &["--status", "{words} words"]);
+    let session = Session::new(&config).unwrap();
+    session
+        .buffer()
+        .lock()
+        .unwrap()
+        .set_message(Some(String::from("Error when saving")));
+
+    let mut terminal = Terminal::new(TestBackend::new(32, 8)).unwrap();
+    assert_snapshot("message", &render(&mut terminal, &session));
+}
+
+#[test]
+fn resize() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = config(&dir.path().join("ui.txt"), &["--status", "{words} words"]);
+    let mut session = Session::new(&config).unwrap();
+    session.type_text("Margins\nshrink");
+
+    let mut terminal = Terminal::new(TestBackend::new(30, 10)).unwrap();
+    assert_snapshot("resize_before", &render(&mut terminal, &session));
+    terminal.backend_mut().resize(20, 8);
+    assert_snapshot("resize_after", &render(&mut terminal, &session));
+}
+
+#[test]
+fn prompt_above_text() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = config(&dir.path().join("ui.txt"), &["--status", "{words} words"]);
+    let mut session = Session::new(&config).unwrap();
+    session.set_prompt("Describe your street", PromptPlacement::Above);
+    session.type_text("Cobbles");
+
+    let mut terminal = Terminal::new(TestBackend::new(32, 14)).unwrap();
+    assert_snapshot("prompt", &render(&mut terminal, &session));
+}
+
+#[test]
+fn blind_mode_hides_existing_text() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("ui.txt");
+    fs::write(&path, "Written yesterday\n").unwrap();
+    let config = config(
+        &path,
+        &[
+            "--append-only",
+            "true",
+            "--blind",
+            "true",
+            "--status",
+            "{words} words",
+        ],
+    );
+    let mut session = Session::new(&config).unwrap();
+    session.type_text("Hidden line\nvisible");
+
+    let mut terminal = Terminal::new(TestBackend::new(32, 10)).unwrap();
+    let rendered = render(&mut terminal, &session);
+    assert!(!rendered.contains("yesterday"));
+    assert_snapshot("blind_append_only", &rendered);
+}